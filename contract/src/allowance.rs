@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+
+use multisig_model::{Member, MultiSigRequest, MultiSigRequestAction};
+use near_sdk::{env, json_types::U128, near, serde_json, AccountId, NearToken};
+
+use crate::MultiSigContract;
+
+pub(crate) const FT_TRANSFER_METHOD_NAME: &str = "ft_transfer";
+
+/// Spending is tracked per token (`None` for NEAR) and per member (`None` if the allowance is shared).
+pub(crate) type SpendingScope = (Option<AccountId>, Option<Member>);
+
+/// Amount spent within the single-confirmation allowance in the current period.
+#[near(serializers=[borsh])]
+#[derive(Clone, Copy)]
+pub(crate) struct Spending {
+    period_start: u64,
+    spent: u128,
+}
+
+/// Allowance spent by a request executed with a single confirmation, credited back if its execution fails.
+#[near(serializers=[borsh])]
+pub(crate) struct AllowanceSpending {
+    scope: SpendingScope,
+    period_start: u64,
+    amount: u128,
+}
+
+#[near(serializers=[json])]
+struct FtTransferArgs {
+    amount: U128,
+}
+
+impl MultiSigContract {
+    // Records the spending and returns it if the request fits into the remaining allowance of the signer
+    pub(crate) fn spend_allowance(
+        &mut self,
+        request: &MultiSigRequest,
+        member: &Member,
+    ) -> Option<Vec<AllowanceSpending>> {
+        let amounts = request_amounts(request)?;
+        let now = env::block_timestamp();
+        let mut spendings = Vec::with_capacity(amounts.len());
+        for (token_id, amount) in amounts {
+            let limit = self.spending_limits.get(&token_id)?;
+            let scope = (token_id, limit.per_member.then(|| member.clone()));
+            let spending = self.current_spending(&scope, limit.period.0, now);
+            let spent = spending.spent.checked_add(amount)?;
+            if spent > limit.amount.0 {
+                return None;
+            }
+            spendings.push((scope, Spending { spent, ..spending }, amount));
+        }
+        let spent_allowance = spendings
+            .into_iter()
+            .map(|(scope, spending, amount)| {
+                self.spendings.insert(scope.clone(), spending);
+                AllowanceSpending {
+                    scope,
+                    period_start: spending.period_start,
+                    amount,
+                }
+            })
+            .collect();
+        Some(spent_allowance)
+    }
+
+    // Credits back the allowance spent by a failed request, unless a new period started since
+    // - used when the execution fails
+    pub(crate) fn refund_allowance(&mut self, spent_allowance: Vec<AllowanceSpending>) {
+        for AllowanceSpending {
+            scope,
+            period_start,
+            amount,
+        } in spent_allowance
+        {
+            if let Some(spending) = self.spendings.get_mut(&scope) {
+                if spending.period_start == period_start {
+                    spending.spent = spending.spent.saturating_sub(amount);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn remaining_allowance(&self, token_id: Option<AccountId>, member: Option<Member>) -> u128 {
+        let Some(limit) = self.spending_limits.get(&token_id) else {
+            return 0;
+        };
+        let member = if limit.per_member {
+            Some(member.expect("Spending limit is per member, member is required"))
+        } else {
+            None
+        };
+        let spending = self.current_spending(&(token_id, member), limit.period.0, env::block_timestamp());
+        limit.amount.0.saturating_sub(spending.spent)
+    }
+
+    // Spending of the current period, a new period starts once the previous one is over
+    fn current_spending(&self, scope: &SpendingScope, period: u64, now: u64) -> Spending {
+        match self.spendings.get(scope) {
+            Some(spending) if now < spending.period_start.saturating_add(period) => *spending,
+            _ => Spending {
+                period_start: now,
+                spent: 0,
+            },
+        }
+    }
+}
+
+// Amounts transferred by the request per token, None if the request does anything else
+fn request_amounts(request: &MultiSigRequest) -> Option<BTreeMap<Option<AccountId>, u128>> {
+    let mut amounts = BTreeMap::new();
+    for action in &request.actions {
+        let (token_id, amount) = match action {
+            MultiSigRequestAction::Transfer { amount } => (None, amount.as_yoctonear()),
+            MultiSigRequestAction::FunctionCall {
+                method_name,
+                args,
+                deposit,
+                ..
+            } if method_name == FT_TRANSFER_METHOD_NAME && *deposit == NearToken::from_yoctonear(1) => {
+                let args: FtTransferArgs = serde_json::from_slice(&args.0).ok()?;
+                (Some(request.receiver_id.clone()), args.amount.0)
+            }
+            _ => return None,
+        };
+        let total: &mut u128 = amounts.entry(token_id).or_default();
+        *total = total.checked_add(amount)?;
+    }
+    (!amounts.is_empty()).then_some(amounts)
+}
@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+
+use multisig_model::{
+    ConfigUpdate, Member, MultiSigRequest, MultiSigRequestAction, MultiSigRequestActionKind, MultisigRequestId,
+    MultisigView, TokenSpendingLimit,
+};
+use near_sdk::{env, Promise};
+
+use crate::{
+    member::{add_function_call_key, signer_permission},
+    policy::assert_valid_policy,
+    recovery::assert_valid_recovery,
+    MultiSigContract,
+};
+
+impl MultiSigContract {
+    // Adds access keys of key members and registers account members - used in new_with_config
+    pub(crate) fn add_initial_members(&mut self, members: Vec<Member>) {
+        let mut keys = Vec::new();
+        for member in members {
+            match member {
+                Member::Key(public_key) => keys.push(public_key),
+                Member::Account(account_id) => self.add_member(account_id),
+            }
+        }
+        if keys.is_empty() {
+            return;
+        }
+        let mut promise = Promise::new(env::current_account_id());
+        for public_key in keys {
+            self.signer_keys.insert(public_key.clone());
+            self.set_key_permission(&public_key, Some(signer_permission(None)));
+            promise = add_function_call_key(promise, public_key, signer_permission(None));
+        }
+    }
+
+    // Time before other members can delete the request, the longest cooldown of its actions - used in delete_request
+    pub(crate) fn request_cooldown_of(&self, request: &MultiSigRequest) -> u64 {
+        request
+            .actions
+            .iter()
+            .map(|action| self.kind_cooldown(action.kind()))
+            .max()
+            .unwrap_or(self.request_cooldown)
+    }
+
+    pub(crate) fn kind_cooldown(&self, kind: MultiSigRequestActionKind) -> u64 {
+        self.cooldown_overrides
+            .get(&kind)
+            .copied()
+            .unwrap_or(self.request_cooldown)
+    }
+
+    // Validates the configuration resulting from the update before changing anything - used in execute_request
+    pub(crate) fn update_config(&mut self, update: ConfigUpdate) {
+        let num_confirmations = update.num_confirmations.unwrap_or(self.num_confirmations);
+        let active_requests_limit = update.active_requests_limit.unwrap_or(self.active_requests_limit);
+        let request_cooldown = update
+            .request_cooldown
+            .map_or(self.request_cooldown, |cooldown| cooldown.0);
+        let cooldown_overrides: BTreeMap<_, _> = match update.cooldown_overrides {
+            Some(overrides) => overrides
+                .into_iter()
+                .map(|(kind, cooldown)| (kind, cooldown.0))
+                .collect(),
+            None => self.cooldown_overrides.clone(),
+        };
+        let request_expiry = match update.request_expiry {
+            Some(expiry) => (expiry.0 > 0).then_some(expiry.0),
+            None => self.request_expiry,
+        };
+
+        assert!(num_confirmations > 0, "Number of confirmations must be positive");
+        // keys added outside of the multisig aren't counted until every signer key is registered
+        if self.signer_keys_complete {
+            assert!(
+                num_confirmations <= self.num_members(),
+                "Number of confirmations must be between 1 and the number of members"
+            );
+        }
+        if let Some(elevated_num_confirmations) = update.elevated_num_confirmations {
+            assert_valid_elevated_num_confirmations(elevated_num_confirmations, num_confirmations, self.num_members());
+        }
+        assert!(active_requests_limit > 0, "Active requests limit must be positive");
+        let longest_cooldown = cooldown_overrides.values().copied().fold(request_cooldown, u64::max);
+        assert_cooldown_within_expiry(longest_cooldown, request_expiry);
+        if let Some(policy) = &update.policy {
+            assert_valid_policy(policy);
+        }
+        if let Some(Some(recovery)) = &update.recovery {
+            assert_valid_recovery(recovery);
+        }
+
+        let previous_thresholds = (self.num_confirmations, self.get_elevated_num_confirmations());
+        self.num_confirmations = num_confirmations;
+        if let Some(elevated_num_confirmations) = update.elevated_num_confirmations {
+            self.elevated_num_confirmations = elevated_num_confirmations;
+        }
+        self.active_requests_limit = active_requests_limit;
+        self.request_cooldown = request_cooldown;
+        self.cooldown_overrides = cooldown_overrides;
+        self.request_expiry = request_expiry;
+        if let Some(retry_config) = update.retry_config {
+            self.retry_config = retry_config;
+        }
+        if let Some(policy) = update.policy {
+            self.policy.set(Some(policy));
+        }
+        if let Some(confirmations_policy) = update.confirmations_policy {
+            self.confirmations_policy = confirmations_policy;
+        }
+        for TokenSpendingLimit { token_id, limit } in update.spending_limits.unwrap_or_default() {
+            match limit {
+                Some(limit) => {
+                    self.spending_limits.insert(token_id, limit);
+                }
+                None => {
+                    self.spending_limits.remove(&token_id);
+                }
+            }
+        }
+        if let Some(guardians) = update.guardians {
+            self.guardians.clear();
+            self.guardians.extend(guardians);
+        }
+        if let Some(recovery) = update.recovery {
+            self.recovery = recovery;
+            self.recovery_proposal = None;
+        }
+        // confirmations collected under a lower threshold might not reflect the will of the members anymore
+        if self.num_confirmations > previous_thresholds.0
+            || self.get_elevated_num_confirmations() > previous_thresholds.1
+        {
+            self.invalidate_confirmations(None);
+        }
+    }
+
+    // Whether the request is too old to be confirmed - used in confirm
+    pub(crate) fn is_expired(&self, request_id: MultisigRequestId) -> bool {
+        let Some(expiry) = self.request_expiry else {
+            return false;
+        };
+        let added_timestamp = self.requests.get(&request_id).expect("No such request").added_timestamp;
+        env::block_timestamp() > added_timestamp.saturating_add(expiry)
+    }
+}
+
+// Rejects configurations the multisig can't work with - used in new_with_config
+pub(crate) fn assert_valid_config(
+    members: &[Member],
+    num_confirmations: usize,
+    elevated_num_confirmations: usize,
+    active_requests_limit: u32,
+    request_cooldown: u64,
+    request_expiry: Option<u64>,
+) {
+    for (index, member) in members.iter().enumerate() {
+        assert!(!members[..index].contains(member), "Members must be unique");
+    }
+    assert!(
+        num_confirmations > 0 && num_confirmations <= members.len(),
+        "Number of confirmations must be between 1 and the number of members"
+    );
+    assert_valid_elevated_num_confirmations(elevated_num_confirmations, num_confirmations, members.len());
+    assert!(active_requests_limit > 0, "Active requests limit must be positive");
+    assert_cooldown_within_expiry(request_cooldown, request_expiry);
+}
+
+// The single-setting actions are kept for compatibility and applied as updates - used in execute_request
+pub(crate) fn config_update(action: MultiSigRequestAction) -> ConfigUpdate {
+    match action {
+        MultiSigRequestAction::SetNumConfirmations { num_confirmations } => ConfigUpdate {
+            num_confirmations: Some(num_confirmations),
+            ..Default::default()
+        },
+        MultiSigRequestAction::SetActiveRequestsLimit { active_requests_limit } => ConfigUpdate {
+            active_requests_limit: Some(active_requests_limit),
+            ..Default::default()
+        },
+        MultiSigRequestAction::SetElevatedNumConfirmations { num_confirmations } => ConfigUpdate {
+            elevated_num_confirmations: Some(num_confirmations),
+            ..Default::default()
+        },
+        MultiSigRequestAction::SetPolicy { policy } => ConfigUpdate {
+            policy: Some(policy),
+            ..Default::default()
+        },
+        MultiSigRequestAction::SetRetryConfig { retry_config } => ConfigUpdate {
+            retry_config: Some(retry_config),
+            ..Default::default()
+        },
+        MultiSigRequestAction::SetRequestCooldown { cooldown, overrides } => ConfigUpdate {
+            request_cooldown: Some(cooldown),
+            cooldown_overrides: Some(overrides),
+            ..Default::default()
+        },
+        MultiSigRequestAction::SetSpendingLimit { token_id, limit } => ConfigUpdate {
+            spending_limits: Some(vec![TokenSpendingLimit { token_id, limit }]),
+            ..Default::default()
+        },
+        MultiSigRequestAction::SetGuardians { guardians } => ConfigUpdate {
+            guardians: Some(guardians),
+            ..Default::default()
+        },
+        MultiSigRequestAction::SetRecovery { recovery } => ConfigUpdate {
+            recovery: Some(recovery),
+            ..Default::default()
+        },
+        MultiSigRequestAction::UpdateConfig { update } => *update,
+        _ => unreachable!("Not a configuration action"),
+    }
+}
+
+// Security-critical requests must need at least the usual confirmations and stay reachable by the members
+fn assert_valid_elevated_num_confirmations(
+    elevated_num_confirmations: usize,
+    num_confirmations: usize,
+    num_members: usize,
+) {
+    assert!(
+        elevated_num_confirmations >= num_confirmations && elevated_num_confirmations <= num_members,
+        "Elevated number of confirmations must be between the number of confirmations and the number of members"
+    );
+}
+
+// Requests must be deletable before they expire
+fn assert_cooldown_within_expiry(request_cooldown: u64, request_expiry: Option<u64>) {
+    if let Some(request_expiry) = request_expiry {
+        assert!(
+            request_expiry > request_cooldown,
+            "Request expiry must be longer than the request cooldown"
+        );
+    }
+}
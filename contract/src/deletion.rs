@@ -0,0 +1,82 @@
+use multisig_model::{Member, MultisigRequestId, RequestOutcome, RequestRecord};
+use near_sdk::env;
+
+use crate::{insert_member_request, remove_member_request, MultiSigContract, StorageKey};
+
+impl MultiSigContract {
+    // Members deleting the pending or failed request: its author alone, otherwise a quorum of members voting after
+    // the cooldown. None while there are not enough votes - used in delete_request
+    pub(crate) fn vote_to_delete(&mut self, request_id: MultisigRequestId, member: Member) -> Option<Vec<Member>> {
+        // the cooldown of a failed request starts when it failed, so its members have time to retry it
+        let (signer, cooldown_start, cooldown) = if let Some(failed) = self.failed_requests.get(&request_id) {
+            (
+                failed.signer.clone(),
+                failed.failed_at,
+                self.request_cooldown_of(&failed.request),
+            )
+        } else {
+            let request_with_signer = self.requests.get(&request_id).expect("No such request");
+            (
+                request_with_signer.signer.clone(),
+                request_with_signer.added_timestamp,
+                self.request_cooldown_of(&request_with_signer.request),
+            )
+        };
+        if signer == member {
+            return Some(vec![member]);
+        }
+        // can't delete requests of other members before the cooldown
+        assert!(
+            env::block_timestamp() > cooldown_start + cooldown,
+            "Request cannot be deleted immediately after creation."
+        );
+        let votes = self.delete_votes.entry(request_id).or_default();
+        assert!(!votes.contains(&member), "Already voted to delete this request");
+        votes.push(member.clone());
+        let votes = (votes.len() >= self.num_confirmations).then(|| votes.clone());
+        insert_member_request(
+            &mut self.delete_voted_requests,
+            member.clone(),
+            request_id,
+            StorageKey::MemberDeleteVotedRequests { member },
+        );
+        votes
+    }
+
+    // Removed members can't vote anymore, their votes to delete requests are dropped - used in remove_signer
+    pub(crate) fn revoke_delete_votes(&mut self, member: &Member) {
+        let Some(mut voted_requests) = self.delete_voted_requests.remove(member) else {
+            return;
+        };
+        let request_ids: Vec<MultisigRequestId> = voted_requests.iter().copied().collect();
+        voted_requests.clear();
+        for request_id in request_ids {
+            let Some(votes) = self.delete_votes.get_mut(&request_id) else {
+                continue;
+            };
+            votes.retain(|vote| vote != member);
+            if votes.is_empty() {
+                self.delete_votes.remove(&request_id);
+            }
+        }
+    }
+
+    // Drops the votes to delete the request once it is removed, executed or retried
+    pub(crate) fn remove_delete_votes(&mut self, request_id: MultisigRequestId) {
+        let Some(votes) = self.delete_votes.remove(&request_id) else {
+            return;
+        };
+        for member in &votes {
+            remove_member_request(&mut self.delete_voted_requests, member, request_id);
+        }
+    }
+
+    // used in delete_request
+    pub(crate) fn record_deletion(&mut self, request_id: MultisigRequestId, deleted_by: Vec<Member>) {
+        self.history.push(RequestRecord {
+            request_id,
+            outcome: RequestOutcome::Deleted { deleted_by },
+            timestamp: env::block_timestamp(),
+        });
+    }
+}
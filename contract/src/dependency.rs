@@ -0,0 +1,108 @@
+use multisig_model::{MultisigRequestId, MultisigView};
+use near_sdk::{store::IterableSet, PromiseOrValue};
+
+use crate::{event::MultisigEvent, retry::ExecutingRequest, MultiSigContract};
+
+impl MultiSigContract {
+    // Records what the new request waits for - used in add_request
+    pub(crate) fn insert_dependencies(&mut self, request_id: MultisigRequestId, depends_on: &[MultisigRequestId]) {
+        let mut dependencies = Vec::new();
+        for dependency in depends_on {
+            assert!(
+                self.requests.contains_key(dependency),
+                "Dependency {dependency} is not pending"
+            );
+            if dependencies.contains(dependency) {
+                continue;
+            }
+            dependencies.push(*dependency);
+            self.dependents.entry(*dependency).or_default().push(request_id);
+        }
+        if !dependencies.is_empty() {
+            self.dependencies.insert(request_id, dependencies);
+        }
+    }
+
+    // Whether the request waits for other requests to be executed - used in confirm
+    pub(crate) fn is_blocked(&self, request_id: MultisigRequestId) -> bool {
+        self.dependencies.contains_key(&request_id)
+    }
+
+    // Unblocks requests waiting for the request, the ones confirmed already are executed with execute_ready or confirm
+    // - used when it succeeds
+    pub(crate) fn release_dependents(&mut self, request_id: MultisigRequestId) {
+        let Some(dependents) = self.dependents.remove(&request_id) else {
+            return;
+        };
+        let mut request_ids = Vec::new();
+        for dependent in dependents {
+            // None if the dependent was removed since
+            let Some(dependencies) = self.dependencies.get_mut(&dependent) else {
+                continue;
+            };
+            dependencies.retain(|dependency| *dependency != request_id);
+            if dependencies.is_empty() {
+                self.dependencies.remove(&dependent);
+                request_ids.push(dependent);
+            }
+        }
+        if !request_ids.is_empty() {
+            MultisigEvent::DependentsReleased {
+                request_id,
+                request_ids,
+            }
+            .emit();
+        }
+    }
+
+    // Removes requests waiting for the request - used when it fails or is removed without execution
+    pub(crate) fn cancel_dependents(&mut self, request_id: MultisigRequestId) {
+        let Some(dependents) = self.dependents.remove(&request_id) else {
+            return;
+        };
+        let mut request_ids = Vec::new();
+        for dependent in dependents {
+            // a dependent might have been cancelled already as a dependent of another one
+            if self.requests.contains_key(&dependent) {
+                self.cancel_request(dependent);
+                request_ids.push(dependent);
+            }
+        }
+        if !request_ids.is_empty() {
+            MultisigEvent::DependentsCancelled {
+                request_id,
+                request_ids,
+            }
+            .emit();
+        }
+    }
+
+    // Whether the confirmations the request has meet the current threshold - used in execute_ready and confirm
+    pub(crate) fn is_ready(&self, request_id: MultisigRequestId) -> bool {
+        let request = &self.requests.get(&request_id).expect("No such request").request;
+        let num_confirmed = self.confirmations.get(&request_id).map_or(0, IterableSet::len);
+        !self.is_blocked(request_id)
+            && !self.is_expired(request_id)
+            && num_confirmed as usize >= self.required_confirmations(request)
+    }
+
+    // Executes the request with the confirmations it has - used in execute_ready and confirm
+    pub(crate) fn execute_ready_request(&mut self, request_id: MultisigRequestId) -> PromiseOrValue<()> {
+        let request_with_signer = self.requests.get(&request_id).unwrap();
+        let signer = request_with_signer.signer.clone();
+        self.assert_not_paused(&request_with_signer.request);
+        let confirmations = self.get_confirmations(request_id);
+        let request = self.remove_request(request_id);
+        self.execute_request(
+            request_id,
+            ExecutingRequest {
+                request,
+                signer,
+                confirmations,
+                attempts: 1,
+                spent_allowance: vec![],
+                batch_results: None,
+            },
+        )
+    }
+}
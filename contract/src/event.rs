@@ -0,0 +1,60 @@
+use multisig_model::{Member, MultisigRequestId, ScheduleId, StreamId};
+use near_sdk::{near, AccountId, PublicKey};
+
+#[near(event_json(standard = "multisig"))]
+pub(crate) enum MultisigEvent {
+    /// Confirmations of a deleted key or removed member account were removed from pending requests.
+    #[event_version("1.0.0")]
+    ConfirmationsRevoked {
+        member: Member,
+        request_ids: Vec<MultisigRequestId>,
+    },
+    /// Confirmations of pending requests were removed by the `ResetAll` confirmations policy.
+    #[event_version("1.0.0")]
+    ConfirmationsReset { request_ids: Vec<MultisigRequestId> },
+    /// The multisig was paused by a guardian key.
+    #[event_version("1.0.0")]
+    Paused { guardian: PublicKey },
+    /// The multisig was resumed by an `Unpause` request.
+    #[event_version("1.0.0")]
+    Unpaused,
+    /// A recovery account proposed new signers.
+    #[event_version("1.0.0")]
+    RecoveryProposed { proposer: AccountId, executable_at: u64 },
+    /// The pending recovery proposal was cancelled by a member.
+    #[event_version("1.0.0")]
+    RecoveryCancelled { member: Member },
+    /// The signers were replaced with the recovery proposal.
+    #[event_version("1.0.0")]
+    RecoveryExecuted { proposer: AccountId },
+    /// A `Schedule` request was executed, its runs can be triggered with `trigger_scheduled`.
+    #[event_version("1.0.0")]
+    ScheduleCreated {
+        schedule_id: ScheduleId,
+        request_id: MultisigRequestId,
+    },
+    /// The last run of the schedule finished and the schedule was removed. Lists whether each run succeeded.
+    #[event_version("1.0.0")]
+    ScheduleCompleted {
+        schedule_id: ScheduleId,
+        results: Vec<bool>,
+    },
+    /// Pending requests were removed because the request they depend on failed or was removed.
+    #[event_version("1.0.0")]
+    DependentsCancelled {
+        request_id: MultisigRequestId,
+        request_ids: Vec<MultisigRequestId>,
+    },
+    /// Pending requests are no longer blocked because the request they depend on succeeded.
+    #[event_version("1.0.0")]
+    DependentsReleased {
+        request_id: MultisigRequestId,
+        request_ids: Vec<MultisigRequestId>,
+    },
+    /// A `CreateStream` request was executed, the receiver can claim the vested tokens with `claim`.
+    #[event_version("1.0.0")]
+    StreamCreated {
+        stream_id: StreamId,
+        request_id: MultisigRequestId,
+    },
+}
@@ -0,0 +1,165 @@
+use multisig_model::{
+    ExecutionResult, MultiSigRequest, MultiSigRequestAction, MultisigRequestId, RequestOutcome, RequestRecord,
+};
+use near_sdk::{env, near, near_bindgen, serde_json, Gas, Promise, PromiseError};
+
+use crate::{MultiSigContract, MultiSigContractExt};
+
+const ON_REQUEST_EXECUTED_GAS: Gas = Gas::from_tgas(20);
+
+const ON_BATCH_EXECUTED_GAS: Gas = Gas::from_tgas(20);
+
+/// Maximum size of the stored return value of an executed request.
+const MAX_RESULT_SIZE: usize = 1024;
+
+/// Return value of the last action of an executed request, `None` if the action failed.
+#[near(serializers=[borsh])]
+pub(crate) struct ExecutionOutput {
+    value: Option<Vec<u8>>,
+    truncated: bool,
+}
+
+impl ExecutionOutput {
+    pub(crate) fn to_result(&self) -> ExecutionResult {
+        let value = self.value.clone().unwrap_or_default();
+        let json = if self.truncated {
+            None
+        } else {
+            serde_json::from_slice(&value).ok()
+        };
+        ExecutionResult {
+            success: self.value.is_some(),
+            value: value.into(),
+            truncated: self.truncated,
+            json,
+        }
+    }
+}
+
+// Reports the outcome of the request once its promise is resolved - used in execute_request
+pub(crate) fn track_execution(promise: Promise, request_id: MultisigRequestId) -> Promise {
+    promise.then(
+        MultiSigContract::ext(env::current_account_id())
+            .with_static_gas(ON_REQUEST_EXECUTED_GAS)
+            .on_request_executed(request_id),
+    )
+}
+
+// Executes the transactions in parallel and records success of each one - used in execute_request
+pub(crate) fn execute_batch(request_id: MultisigRequestId, transactions: &[MultiSigRequest]) -> Promise {
+    transactions
+        .iter()
+        .map(transaction_promise)
+        .reduce(Promise::and)
+        .expect("Batch must have transactions")
+        .then(
+            MultiSigContract::ext(env::current_account_id())
+                .with_static_gas(ON_BATCH_EXECUTED_GAS)
+                .on_batch_executed(request_id),
+        )
+}
+
+// Promise of a transaction nested in a request - used for schedules and batches
+pub(crate) fn transaction_promise(request: &MultiSigRequest) -> Promise {
+    let mut promise = Promise::new(request.receiver_id.clone());
+    for action in &request.actions {
+        promise = match action {
+            MultiSigRequestAction::Transfer { amount } => promise.transfer(*amount),
+            MultiSigRequestAction::FunctionCall {
+                method_name,
+                args,
+                deposit,
+                gas,
+            } => promise.function_call(method_name.clone(), args.0.clone(), *deposit, *gas),
+            _ => env::panic_str("Only Transfer and FunctionCall can be nested in a request"),
+        };
+    }
+    promise
+}
+
+// Rejects nested transactions that can't be executed - used when Schedule and Batch are added
+pub(crate) fn assert_valid_transaction(request: &MultiSigRequest, error: &str) {
+    assert!(!request.actions.is_empty(), "Nested request must have actions");
+    assert!(
+        request.depends_on.is_empty(),
+        "Nested request can't depend on other requests"
+    );
+    for action in &request.actions {
+        assert!(
+            matches!(
+                action,
+                MultiSigRequestAction::Transfer { .. } | MultiSigRequestAction::FunctionCall { .. }
+            ),
+            "{error}"
+        );
+    }
+}
+
+impl MultiSigContract {
+    // Records the outcome and releases or cancels requests depending on the executed one
+    // - used in execute_request and its callbacks
+    pub(crate) fn finish_request(&mut self, request_id: MultisigRequestId, outcome: RequestOutcome) {
+        let is_success = outcome.is_success();
+        self.history.push(RequestRecord {
+            request_id,
+            outcome,
+            timestamp: env::block_timestamp(),
+        });
+        if is_success {
+            if let Some(executing) = self.executing.remove(&request_id) {
+                self.unstage_request_code(&executing.request);
+            }
+            self.release_dependents(request_id);
+        } else {
+            env::log_str(&format!("Request {request_id} failed"));
+            self.fail_request(request_id);
+        }
+    }
+}
+
+#[near_bindgen]
+impl MultiSigContract {
+    /// Handles the outcome of the executed request and stores the value returned by its last action.
+    #[private]
+    pub fn on_request_executed(&mut self, request_id: MultisigRequestId) {
+        // a longer value isn't read, so the gas of the callback doesn't depend on the called contract
+        let output = match env::promise_result_checked(0, MAX_RESULT_SIZE) {
+            Ok(value) => ExecutionOutput {
+                value: Some(value),
+                truncated: false,
+            },
+            Err(PromiseError::Failed) => ExecutionOutput {
+                value: None,
+                truncated: false,
+            },
+            Err(_) => ExecutionOutput {
+                value: Some(vec![]),
+                truncated: true,
+            },
+        };
+        let outcome = if output.value.is_some() {
+            RequestOutcome::Succeeded
+        } else {
+            RequestOutcome::Failed
+        };
+        self.execution_results.insert(request_id, output);
+        self.finish_request(request_id, outcome);
+    }
+
+    /// Records success of each transaction of the executed batch, a failed batch keeps them to retry only the failed ones.
+    #[private]
+    pub fn on_batch_executed(&mut self, request_id: MultisigRequestId) {
+        // only the success is kept, so the returned values aren't read
+        let executed = (0..env::promise_results_count())
+            .map(|index| !matches!(env::promise_result_checked(index, 0), Err(PromiseError::Failed)));
+        let results: Vec<bool> = match self.executing.get_mut(&request_id) {
+            Some(executing) => {
+                let results = executing.batch_results(executed);
+                executing.batch_results = Some(results.clone());
+                results
+            }
+            None => executed.collect(),
+        };
+        self.finish_request(request_id, RequestOutcome::Batch { results });
+    }
+}
@@ -0,0 +1,48 @@
+use multisig_model::{ConfirmationsPolicy, Member, MultisigRequestId};
+
+use crate::{event::MultisigEvent, remove_member_request, MultiSigContract};
+
+impl MultiSigContract {
+    // Applies the confirmations policy to pending and failed requests after members changed or the threshold was
+    // raised, with the member that was removed if any - used in execute_request and execute_recovery
+    pub(crate) fn invalidate_confirmations(&mut self, removed: Option<&Member>) {
+        match self.confirmations_policy {
+            ConfirmationsPolicy::Keep => {}
+            ConfirmationsPolicy::DropRemoved => {
+                if let Some(member) = removed {
+                    self.revoke_confirmations(member);
+                    for failed in self.failed_requests.values_mut() {
+                        failed.confirmations.retain(|confirmation| confirmation != member);
+                    }
+                }
+            }
+            ConfirmationsPolicy::ResetAll => {
+                self.reset_confirmations();
+                for failed in self.failed_requests.values_mut() {
+                    failed.confirmations.clear();
+                }
+            }
+        }
+    }
+
+    // Removes all confirmations of pending requests
+    fn reset_confirmations(&mut self) {
+        let pending_ids: Vec<MultisigRequestId> = self.requests.keys().copied().collect();
+        let mut request_ids = Vec::new();
+        for request_id in pending_ids {
+            let confirmations = self.confirmations.get_mut(&request_id).unwrap();
+            if confirmations.is_empty() {
+                continue;
+            }
+            let members: Vec<Member> = confirmations.iter().cloned().collect();
+            confirmations.clear();
+            for member in &members {
+                remove_member_request(&mut self.confirmed_requests, member, request_id);
+            }
+            request_ids.push(request_id);
+        }
+        if !request_ids.is_empty() {
+            MultisigEvent::ConfirmationsReset { request_ids }.emit();
+        }
+    }
+}
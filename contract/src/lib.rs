@@ -1,136 +1,558 @@
-use std::collections::HashSet;
+use std::collections::BTreeMap;
 
 use multisig_model::{
-    MultiSigRequest, MultiSigRequestAction, MultiSigRequestWithSigner, MultisigApi, MultisigRequestId, MultisigView,
+    ConfigUpdate, ConfirmationsPolicy, DeleteOutcome, ExecutionResult, FailedRequest, FunctionCallPermission, Member,
+    MultiSigRequest, MultiSigRequestAction, MultiSigRequestActionKind, MultiSigRequestWithSigner, MultisigApi,
+    MultisigConfig, MultisigRequestId, MultisigView, PauseInfo, Policy, RecoveryConfig, RecoveryProposal,
+    RequestOutcome, RequestRecord, RetryConfig, ScheduleId, ScheduledRequest, SignerKey, SpendingLimit, StagedCodeInfo,
+    Stream, StreamId, UpgradeRecord,
 };
 use near_sdk::{
-    collections::UnorderedMap, env, near, near_bindgen, AccountId, NearToken, PanicOnDefault, Promise, PromiseOrValue,
-    PublicKey,
+    env,
+    json_types::{Base58CryptoHash, Base64VecU8, U128, U64},
+    near, near_bindgen,
+    store::{IterableMap, IterableSet, LazyOption, LookupMap, LookupSet, Vector},
+    AccountId, BorshStorageKey, CryptoHash, Gas, PanicOnDefault, Promise, PromiseOrValue, PublicKey,
 };
 
-/// Unlimited allowance for multisig keys.
-const DEFAULT_ALLOWANCE: NearToken = NearToken::from_yoctonear(0);
+use crate::{
+    allowance::{Spending, SpendingScope},
+    config::{assert_valid_config, config_update},
+    event::MultisigEvent,
+    execution::{assert_valid_transaction, execute_batch, track_execution, ExecutionOutput},
+    member::{add_function_call_key, signer_permission},
+    policy::assert_valid_policy,
+    recovery::{apply_recovery, assert_valid_proposal, assert_valid_recovery},
+    retry::ExecutingRequest,
+    schedule::assert_valid_schedule,
+    staging::StagedCode,
+    upgrade::upgrade_self,
+    vesting::assert_valid_stream,
+};
+
+mod allowance;
+mod config;
+mod deletion;
+mod dependency;
+mod event;
+mod execution;
+mod invalidation;
+mod member;
+mod migration;
+mod pause;
+mod policy;
+mod recovery;
+mod retry;
+mod schedule;
+mod staging;
+mod upgrade;
+mod vesting;
 
 // Request cooldown period (time before a request can be deleted)
 const REQUEST_COOLDOWN: u64 = 900_000_000_000;
 
+#[near(serializers=[borsh])]
+#[derive(BorshStorageKey)]
+pub(crate) enum StorageKey {
+    Requests,
+    Confirmations,
+    RequestConfirmations { request_id: MultisigRequestId },
+    RequestsBySigner,
+    SignerRequests { member: Member },
+    ConfirmedRequests,
+    MemberConfirmedRequests { member: Member },
+    RevokedKeys,
+    Upgrades,
+    StagedCode,
+    SpendingLimits,
+    Spendings,
+    Policy,
+    Guardians,
+    Schedules,
+    Streams,
+    Dependencies,
+    Dependents,
+    History,
+    Executing,
+    FailedRequests,
+    ExecutionResults,
+    Members,
+    DeleteVotes,
+    SignerKeys,
+    KeyPermissions,
+    StagedChunks,
+    DeleteVotedRequests,
+    MemberDeleteVotedRequests { member: Member },
+    LastActivity,
+    StagedSizes,
+}
+
 #[near(contract_state)]
 #[derive(PanicOnDefault)]
 pub struct MultiSigContract {
     num_confirmations: usize,
     request_nonce: MultisigRequestId,
-    requests: UnorderedMap<MultisigRequestId, MultiSigRequestWithSigner>,
-    confirmations: UnorderedMap<MultisigRequestId, HashSet<PublicKey>>,
-    num_requests_pk: UnorderedMap<PublicKey, u32>,
-    // per key
+    requests: IterableMap<MultisigRequestId, MultiSigRequestWithSigner>,
+    // a separate set per request, so a confirmation doesn't rewrite the others
+    confirmations: LookupMap<MultisigRequestId, IterableSet<Member>>,
+    // ids of pending requests by the member that added them, its size is num_requests_pk
+    requests_by_signer: LookupMap<Member, IterableSet<MultisigRequestId>>,
+    // ids of pending requests confirmed by the member
+    confirmed_requests: LookupMap<Member, IterableSet<MultisigRequestId>>,
+    // keys removed with DeleteKey and not added back since
+    revoked_keys: LookupSet<PublicKey>,
+    // per member
     active_requests_limit: u32,
+    // time before a request can be deleted
+    request_cooldown: u64,
+    // cooldowns of requests containing actions of the kind
+    cooldown_overrides: BTreeMap<MultiSigRequestActionKind, u64>,
+    // time after which a request can't be confirmed, never if not set
+    request_expiry: Option<u64>,
+    // applied to pending confirmations when members change or the threshold is raised
+    confirmations_policy: ConfirmationsPolicy,
+    // required for security-critical requests, never lower than num_confirmations
+    elevated_num_confirmations: usize,
+    // hash of the code deployed by the last UpgradeSelf request
+    code_hash: Option<Base58CryptoHash>,
+    upgrades: Vector<UpgradeRecord>,
+    // code uploaded with stage_code by its sha256
+    staged_code: LookupMap<CryptoHash, StagedCode>,
+    // bytes of the staged code by its sha256 and the index of the chunk
+    staged_chunks: LookupMap<(CryptoHash, u32), Vec<u8>>,
+    // size of the code staged by each member, reserved when the upload starts
+    staged_sizes: LookupMap<Member, u64>,
+    // limits of transfers executable with a single confirmation by token, None for NEAR
+    spending_limits: LookupMap<Option<AccountId>, SpendingLimit>,
+    spendings: LookupMap<SpendingScope, Spending>,
+    // restrictions on requests, none if not set
+    policy: LazyOption<Policy>,
+    // keys that can pause the multisig alone
+    guardians: IterableSet<PublicKey>,
+    paused: Option<PauseInfo>,
+    // accounts that can replace the signers, none if not set
+    recovery: Option<RecoveryConfig>,
+    recovery_proposal: Option<RecoveryProposal>,
+    // requests executed repeatedly, created by Schedule requests
+    schedules: IterableMap<ScheduleId, ScheduledRequest>,
+    schedule_nonce: ScheduleId,
+    // tokens vesting to receivers, created by CreateStream requests
+    streams: IterableMap<StreamId, Stream>,
+    stream_nonce: StreamId,
+    // dependencies of pending requests that are not executed yet
+    dependencies: LookupMap<MultisigRequestId, Vec<MultisigRequestId>>,
+    // pending requests waiting for the request
+    dependents: LookupMap<MultisigRequestId, Vec<MultisigRequestId>>,
+    // outcomes of executed requests
+    history: Vector<RequestRecord>,
+    // requests whose promises are not resolved yet
+    executing: LookupMap<MultisigRequestId, ExecutingRequest>,
+    failed_requests: IterableMap<MultisigRequestId, FailedRequest>,
+    retry_config: RetryConfig,
+    // values returned by the last action of executed requests
+    execution_results: LookupMap<MultisigRequestId, ExecutionOutput>,
+    // accounts confirming by calling the multisig directly, members in addition to the access keys
+    members: IterableSet<AccountId>,
+    // members that voted to delete a pending or failed request of another member
+    delete_votes: LookupMap<MultisigRequestId, Vec<Member>>,
+    // ids of requests the member voted to delete
+    delete_voted_requests: LookupMap<Member, IterableSet<MultisigRequestId>>,
+    // access keys known to be signers, all of them must confirm a full access key for the multisig
+    signer_keys: IterableSet<PublicKey>,
+    // whether signer_keys holds every signer key, false while keys added outside of the multisig aren't registered
+    signer_keys_complete: bool,
+    // permissions of signer function call keys added or registered by the multisig, kept to rotate them
+    key_permissions: LookupMap<PublicKey, FunctionCallPermission>,
+    // when the member last acted, recovery can only remove keys inactive for a while
+    last_activity: LookupMap<Member, u64>,
 }
 
 #[near_bindgen]
 impl MultisigApi for MultiSigContract {
     /// Initialize multisig contract.
-    /// @params num_confirmations: k of n signatures required to perform operations.
+    /// @params `num_confirmations`: k of n signatures required to perform operations.
     #[init]
     fn new(num_confirmations: usize) -> Self {
         assert!(!env::state_exists(), "Already initialized");
+        assert!(num_confirmations > 0, "Number of confirmations must be positive");
         Self {
             num_confirmations,
             request_nonce: 0,
-            requests: UnorderedMap::new(b"r".to_vec()),
-            confirmations: UnorderedMap::new(b"c".to_vec()),
-            num_requests_pk: UnorderedMap::new(b"k".to_vec()),
+            requests: IterableMap::new(StorageKey::Requests),
+            confirmations: LookupMap::new(StorageKey::Confirmations),
+            requests_by_signer: LookupMap::new(StorageKey::RequestsBySigner),
+            confirmed_requests: LookupMap::new(StorageKey::ConfirmedRequests),
+            revoked_keys: LookupSet::new(StorageKey::RevokedKeys),
             active_requests_limit: 12,
+            request_cooldown: REQUEST_COOLDOWN,
+            cooldown_overrides: BTreeMap::new(),
+            request_expiry: None,
+            confirmations_policy: ConfirmationsPolicy::default(),
+            elevated_num_confirmations: num_confirmations,
+            code_hash: None,
+            upgrades: Vector::new(StorageKey::Upgrades),
+            staged_code: LookupMap::new(StorageKey::StagedCode),
+            staged_chunks: LookupMap::new(StorageKey::StagedChunks),
+            staged_sizes: LookupMap::new(StorageKey::StagedSizes),
+            spending_limits: LookupMap::new(StorageKey::SpendingLimits),
+            spendings: LookupMap::new(StorageKey::Spendings),
+            policy: LazyOption::new(StorageKey::Policy, None),
+            guardians: IterableSet::new(StorageKey::Guardians),
+            paused: None,
+            recovery: None,
+            recovery_proposal: None,
+            schedules: IterableMap::new(StorageKey::Schedules),
+            schedule_nonce: 0,
+            streams: IterableMap::new(StorageKey::Streams),
+            stream_nonce: 0,
+            dependencies: LookupMap::new(StorageKey::Dependencies),
+            dependents: LookupMap::new(StorageKey::Dependents),
+            history: Vector::new(StorageKey::History),
+            executing: LookupMap::new(StorageKey::Executing),
+            failed_requests: IterableMap::new(StorageKey::FailedRequests),
+            retry_config: RetryConfig::default(),
+            execution_results: LookupMap::new(StorageKey::ExecutionResults),
+            members: IterableSet::new(StorageKey::Members),
+            delete_votes: LookupMap::new(StorageKey::DeleteVotes),
+            delete_voted_requests: LookupMap::new(StorageKey::DeleteVotedRequests),
+            signer_keys: IterableSet::new(StorageKey::SignerKeys),
+            // the keys are added outside of the multisig
+            signer_keys_complete: false,
+            key_permissions: LookupMap::new(StorageKey::KeyPermissions),
+            last_activity: LookupMap::new(StorageKey::LastActivity),
         }
     }
 
+    /// Initialize multisig contract with its members and configuration.
+    /// Access keys are added for key members, account members confirm by calling the multisig directly.
+    #[init]
+    fn new_with_config(
+        members: Vec<Member>,
+        num_confirmations: usize,
+        elevated_num_confirmations: Option<usize>,
+        active_requests_limit: Option<u32>,
+        request_cooldown: Option<U64>,
+        request_expiry: Option<U64>,
+    ) -> Self {
+        let mut contract = Self::new(num_confirmations);
+        contract.elevated_num_confirmations = elevated_num_confirmations.unwrap_or(num_confirmations);
+        contract.active_requests_limit = active_requests_limit.unwrap_or(contract.active_requests_limit);
+        contract.request_cooldown = request_cooldown.map_or(contract.request_cooldown, |cooldown| cooldown.0);
+        contract.request_expiry = request_expiry.map(|expiry| expiry.0);
+        assert_valid_config(
+            &members,
+            num_confirmations,
+            contract.elevated_num_confirmations,
+            contract.active_requests_limit,
+            contract.request_cooldown,
+            contract.request_expiry,
+        );
+        contract.add_initial_members(members);
+        contract.signer_keys_complete = true;
+        contract
+    }
+
     /// Add request for multisig.
     fn add_request(&mut self, request: MultiSigRequest) -> MultisigRequestId {
-        assert_eq!(
-            env::current_account_id(),
-            env::predecessor_account_id(),
-            "Predecessor account must much current account"
-        );
-        // track how many requests this key has made
-        let num_requests = self.num_requests_pk.get(&env::signer_account_pk()).unwrap_or(0) + 1;
+        let signer = self.assert_member();
+        self.register_signer_key(&signer);
+        self.record_activity(&signer);
+        assert_valid_actions(&request);
+        self.assert_not_paused(&request);
+        self.assert_allowed_by_policy(&request);
+        self.assert_signer_keys_known(&request);
+        self.reference_staged_code(&request);
+        // track how many requests this member has made
+        let num_requests = self.requests_by_signer.get(&signer).map_or(0, IterableSet::len) + 1;
         assert!(
             num_requests <= self.active_requests_limit,
             "Account has too many active requests. Confirm or delete some."
         );
-        self.num_requests_pk.insert(&env::signer_account_pk(), &num_requests);
         // add the request
+        let request_id = self.request_nonce;
+        self.insert_dependencies(request_id, &request.depends_on);
+        insert_member_request(
+            &mut self.requests_by_signer,
+            signer.clone(),
+            request_id,
+            StorageKey::SignerRequests { member: signer.clone() },
+        );
         let request_added = MultiSigRequestWithSigner {
-            signer_pk: env::signer_account_pk(),
-            added_timestamp: env::block_timestamp(),
             request,
+            signer,
+            added_timestamp: env::block_timestamp(),
         };
-        self.requests.insert(&self.request_nonce, &request_added);
-        let confirmations = HashSet::new();
-        self.confirmations.insert(&self.request_nonce, &confirmations);
+        self.requests.insert(request_id, request_added);
+        self.confirmations.insert(
+            request_id,
+            IterableSet::new(StorageKey::RequestConfirmations { request_id }),
+        );
         self.request_nonce += 1;
-        self.request_nonce - 1
+        request_id
     }
 
     /// Add request for multisig and confirm with the pk that added.
     fn add_request_and_confirm(&mut self, request: MultiSigRequest) -> MultisigRequestId {
         let request_id = self.add_request(request);
-        self.confirm(request_id);
+        self.confirm(request_id).detach();
         request_id
     }
 
     /// Remove given request and associated confirmations.
-    fn delete_request(&mut self, request_id: MultisigRequestId) -> MultiSigRequest {
-        self.assert_valid_request(request_id);
-        let request_with_signer = self.requests.get(&request_id).expect("No such request");
-        // can't delete requests before 15min
-        assert!(
-            env::block_timestamp() > request_with_signer.added_timestamp + REQUEST_COOLDOWN,
-            "Request cannot be deleted immediately after creation."
-        );
-        self.remove_request(request_id)
+    /// The member that added the request can remove it at any time. Other members can vote to remove it once
+    /// the cooldown is over, and it is removed when `num_confirmations` members voted.
+    /// Failed requests are removed the same way, their cooldown starts when they failed.
+    fn delete_request(&mut self, request_id: MultisigRequestId) -> DeleteOutcome {
+        let is_failed = self.failed_requests.contains_key(&request_id);
+        let member = if is_failed {
+            self.assert_member()
+        } else {
+            self.assert_valid_request(request_id)
+        };
+        self.record_activity(&member);
+        let Some(deleted_by) = self.vote_to_delete(request_id, member) else {
+            return DeleteOutcome::Voted {
+                votes: u32::try_from(self.get_delete_votes(request_id).len()).unwrap(),
+            };
+        };
+        self.record_deletion(request_id, deleted_by);
+        let request = if is_failed {
+            self.delete_failed_request(request_id)
+        } else {
+            self.cancel_request(request_id)
+        };
+        DeleteOutcome::Deleted { request }
     }
 
-    /// Confirm given request with given signing key.
+    /// Confirm given request with given signing key, or as the member account calling the multisig.
     /// If with this, there has been enough confirmation, a promise with request will be scheduled.
+    /// Transfers within the spending limit are executed with a single confirmation.
+    /// Requests waiting for other requests only collect confirmations until those are executed.
     fn confirm(&mut self, request_id: MultisigRequestId) -> PromiseOrValue<()> {
-        self.assert_valid_request(request_id);
-        let mut confirmations = self.confirmations.get(&request_id).unwrap();
+        let member = self.assert_valid_request(request_id);
+        self.register_signer_key(&member);
+        self.record_activity(&member);
+        if self.is_ready(request_id) {
+            // keys added before execute_ready existed can't call it, they execute ready requests by confirming them
+            return self.execute_ready_request(request_id);
+        }
+        let confirmations = self.confirmations.get(&request_id).unwrap();
         assert!(
-            !confirmations.contains(&env::signer_account_pk()),
-            "Already confirmed this request with this key"
+            !confirmations.contains(&member),
+            "Already confirmed this request with this key or account"
         );
-        if confirmations.len() + 1 >= self.num_confirmations {
+        assert!(!self.is_expired(request_id), "Request has expired");
+        let num_confirmed = confirmations.len() as usize;
+        let request = self.requests.get(&request_id).unwrap().request.clone();
+        self.assert_not_paused(&request);
+        let is_confirmed = num_confirmed + 1 >= self.required_confirmations(&request);
+        let is_blocked = self.is_blocked(request_id);
+        let spent_allowance = if is_blocked || is_confirmed {
+            None
+        } else {
+            self.spend_allowance(&request, &member)
+        };
+        if !is_blocked && (is_confirmed || spent_allowance.is_some()) {
+            let mut confirmations = self.get_confirmations(request_id);
+            confirmations.push(member);
+            let signer = self.requests.get(&request_id).unwrap().signer.clone();
             let request = self.remove_request(request_id);
             /********************************
-            NOTE: If the tx execution fails for any reason, the request and confirmations are removed already, so the client has to start all over
+            NOTE: If the tx execution fails, the request is kept as failed with its confirmations and can be retried with `retry`,
+            the allowance it spent is credited back
             ********************************/
-            self.execute_request(request)
+            self.execute_request(
+                request_id,
+                ExecutingRequest {
+                    request,
+                    signer,
+                    confirmations,
+                    attempts: 1,
+                    spent_allowance: spent_allowance.unwrap_or_default(),
+                    batch_results: None,
+                },
+            )
         } else {
-            confirmations.insert(env::signer_account_pk());
-            self.confirmations.insert(&request_id, &confirmations);
+            self.confirmations.get_mut(&request_id).unwrap().insert(member.clone());
+            insert_member_request(
+                &mut self.confirmed_requests,
+                member.clone(),
+                request_id,
+                StorageKey::MemberConfirmedRequests { member },
+            );
             PromiseOrValue::Value(())
         }
     }
+
+    /// Execute the pending request if the confirmations it has meet the current threshold.
+    fn execute_ready(&mut self, request_id: MultisigRequestId) -> PromiseOrValue<()> {
+        self.assert_member();
+        assert!(self.is_ready(request_id), "Request is not ready to be executed");
+        self.execute_ready_request(request_id)
+    }
+
+    /// Append a chunk of code to be deployed later with `DeployStagedCode`. `code_size` is the size of the whole code,
+    /// the same for every chunk, the hash is checked once the uploaded chunks reach it.
+    /// Returns the size of the code uploaded so far, `get_staged_code` shows whether the upload is complete.
+    fn stage_code(&mut self, code_hash: Base58CryptoHash, code_size: U64, chunk: Base64VecU8) -> U64 {
+        let member = self.assert_member();
+        self.append_staged_chunk(member, code_hash.into(), code_size.0, chunk.into())
+            .into()
+    }
+
+    /// Remove staged code no request deploys, e.g. an abandoned upload.
+    /// Only the uploader can remove it, unless they are no longer a member.
+    fn unstage_code(&mut self, code_hash: Base58CryptoHash) {
+        let member = self.assert_member();
+        self.unstage(member, code_hash.into());
+    }
+
+    /// Pause the multisig with a guardian key. Only an `Unpause` request can be added, confirmed and executed
+    /// until the multisig is unpaused.
+    fn pause(&mut self) {
+        assert_eq!(
+            env::current_account_id(),
+            env::predecessor_account_id(),
+            "Predecessor account must much current account"
+        );
+        let guardian = env::signer_account_pk();
+        assert!(
+            self.guardians.contains(&guardian),
+            "Only guardian keys can pause the multisig"
+        );
+        assert!(self.paused.is_none(), "Multisig is already paused");
+        self.paused = Some(PauseInfo {
+            guardian: guardian.clone(),
+            timestamp: env::block_timestamp(),
+        });
+        MultisigEvent::Paused { guardian }.emit();
+    }
+
+    /// Propose new signers and threshold, called by a recovery account.
+    /// The proposal can be executed after the recovery delay unless a member cancels it.
+    fn propose_recovery(&mut self, keys: Vec<PublicKey>, removed_keys: Vec<PublicKey>, num_confirmations: usize) {
+        let recovery = self.recovery.as_ref().expect("Recovery is not set");
+        let proposer = env::predecessor_account_id();
+        assert!(
+            recovery.accounts.contains(&proposer),
+            "Only recovery accounts can propose recovery"
+        );
+        assert!(self.recovery_proposal.is_none(), "Recovery is already proposed");
+        assert!(
+            num_confirmations > 0 && num_confirmations <= keys.len(),
+            "Number of confirmations must be between 1 and the number of proposed keys"
+        );
+        assert_valid_proposal(&keys, &removed_keys);
+        self.assert_inactive_keys(&removed_keys, recovery.inactivity_period.0);
+        let executable_at = env::block_timestamp() + recovery.delay.0;
+        MultisigEvent::RecoveryProposed {
+            proposer: proposer.clone(),
+            executable_at,
+        }
+        .emit();
+        self.recovery_proposal = Some(RecoveryProposal {
+            proposer,
+            keys,
+            removed_keys,
+            num_confirmations,
+            proposed_at: env::block_timestamp(),
+            executable_at,
+        });
+    }
+
+    /// Cancel the pending recovery proposal with a single member key or account.
+    fn cancel_recovery(&mut self) {
+        let member = self.assert_member();
+        self.recovery_proposal.take().expect("No recovery proposal");
+        self.record_activity(&member);
+        MultisigEvent::RecoveryCancelled { member }.emit();
+    }
+
+    /// Replace the signers with the pending recovery proposal once its delay is over.
+    /// Can be called by anyone, e.g. a recovery account when the remaining members can't sign.
+    fn execute_recovery(&mut self) -> PromiseOrValue<()> {
+        assert!(self.paused.is_none(), "Multisig is paused");
+        let proposal = self.recovery_proposal.take().expect("No recovery proposal");
+        assert!(
+            env::block_timestamp() >= proposal.executable_at,
+            "Recovery delay is not over yet"
+        );
+        apply_recovery(proposal).into()
+    }
+
+    /// Execute the next due run of the schedule. Can be called by anyone, e.g. a keeper bot.
+    fn trigger_scheduled(&mut self, schedule_id: ScheduleId) -> PromiseOrValue<()> {
+        self.trigger_schedule(schedule_id).into()
+    }
+
+    /// Withdraw the vested and not yet claimed portion of the stream, called by its receiver.
+    fn claim(&mut self, stream_id: StreamId) -> PromiseOrValue<()> {
+        self.claim_stream(stream_id).into()
+    }
+
+    /// Execute the failed request again with the confirmations it has, optionally raising the gas of its function calls.
+    fn retry(&mut self, request_id: MultisigRequestId, gas: Option<Gas>) -> PromiseOrValue<()> {
+        self.retry_request(request_id, gas)
+    }
 }
 
 #[near_bindgen]
 impl MultisigView for MultiSigContract {
     fn get_request(&self, request_id: MultisigRequestId) -> MultiSigRequest {
-        (self.requests.get(&request_id).expect("No such request")).request
+        self.requests.get(&request_id).expect("No such request").request.clone()
     }
 
     fn get_num_requests_pk(&self, public_key: PublicKey) -> u32 {
-        self.num_requests_pk.get(&public_key).unwrap_or(0)
+        self.requests_by_signer
+            .get(&Member::Key(public_key))
+            .map_or(0, IterableSet::len)
+    }
+
+    fn list_requests_by_signer(&self, signer: Member) -> Vec<MultisigRequestId> {
+        self.requests_by_signer
+            .get(&signer)
+            .map(|request_ids| request_ids.iter().copied().collect())
+            .unwrap_or_default()
     }
 
     fn list_request_ids(&self) -> Vec<MultisigRequestId> {
-        self.requests.keys().collect()
+        self.requests.keys().copied().collect()
+    }
+
+    fn list_ready_requests(&self) -> Vec<MultisigRequestId> {
+        self.requests
+            .iter()
+            .filter(|(request_id, request)| !self.is_paused_for(&request.request) && self.is_ready(**request_id))
+            .map(|(request_id, _)| *request_id)
+            .collect()
     }
 
-    fn get_confirmations(&self, request_id: MultisigRequestId) -> Vec<PublicKey> {
+    fn get_confirmations(&self, request_id: MultisigRequestId) -> Vec<Member> {
         self.confirmations
             .get(&request_id)
             .expect("No such request")
-            .into_iter()
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    fn get_delete_votes(&self, request_id: MultisigRequestId) -> Vec<Member> {
+        self.delete_votes.get(&request_id).cloned().unwrap_or_default()
+    }
+
+    fn get_dependencies(&self, request_id: MultisigRequestId) -> Vec<MultisigRequestId> {
+        self.dependencies.get(&request_id).cloned().unwrap_or_default()
+    }
+
+    fn get_revoked_confirmations(&self, request_id: MultisigRequestId) -> Vec<PublicKey> {
+        self.confirmations
+            .get(&request_id)
+            .expect("No such request")
+            .iter()
+            .filter_map(|member| match member {
+                Member::Key(public_key) if self.revoked_keys.contains(public_key) => Some(public_key.clone()),
+                _ => None,
+            })
             .collect()
     }
 
@@ -138,9 +560,136 @@ impl MultisigView for MultiSigContract {
         self.num_confirmations
     }
 
+    fn get_request_cooldown(&self, kind: Option<MultiSigRequestActionKind>) -> U64 {
+        kind.map_or(self.request_cooldown, |kind| self.kind_cooldown(kind))
+            .into()
+    }
+
+    fn get_config(&self) -> MultisigConfig {
+        MultisigConfig {
+            num_confirmations: self.num_confirmations,
+            elevated_num_confirmations: self.get_elevated_num_confirmations(),
+            active_requests_limit: self.active_requests_limit,
+            request_cooldown: self.request_cooldown.into(),
+            cooldown_overrides: self
+                .cooldown_overrides
+                .iter()
+                .map(|(kind, cooldown)| (*kind, U64(*cooldown)))
+                .collect(),
+            request_expiry: self.request_expiry.map(Into::into),
+            retry_config: self.retry_config.clone(),
+            confirmations_policy: self.confirmations_policy,
+        }
+    }
+
     fn get_request_nonce(&self) -> u32 {
         self.request_nonce
     }
+
+    fn get_elevated_num_confirmations(&self) -> usize {
+        self.elevated_num_confirmations.max(self.num_confirmations)
+    }
+
+    fn get_code_hash(&self) -> Option<Base58CryptoHash> {
+        self.code_hash
+    }
+
+    fn list_upgrades(&self) -> Vec<UpgradeRecord> {
+        self.upgrades.iter().cloned().collect()
+    }
+
+    fn list_history(&self, from_index: u32, limit: u32) -> Vec<RequestRecord> {
+        self.history
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect()
+    }
+
+    fn list_failed_request_ids(&self) -> Vec<MultisigRequestId> {
+        self.failed_requests.keys().copied().collect()
+    }
+
+    fn get_failed_request(&self, request_id: MultisigRequestId) -> Option<FailedRequest> {
+        self.failed_requests.get(&request_id).cloned()
+    }
+
+    fn get_retry_config(&self) -> RetryConfig {
+        self.retry_config.clone()
+    }
+
+    fn get_execution_result(&self, request_id: MultisigRequestId) -> Option<ExecutionResult> {
+        self.execution_results.get(&request_id).map(ExecutionOutput::to_result)
+    }
+
+    fn get_staged_code(&self, code_hash: Base58CryptoHash) -> Option<StagedCodeInfo> {
+        self.staged_code_info(code_hash.into())
+    }
+
+    fn get_spending_limit(&self, token_id: Option<AccountId>) -> Option<SpendingLimit> {
+        self.spending_limits.get(&token_id).cloned()
+    }
+
+    fn get_remaining_allowance(&self, token_id: Option<AccountId>, member: Option<Member>) -> U128 {
+        self.remaining_allowance(token_id, member).into()
+    }
+
+    fn get_policy(&self) -> Policy {
+        self.policy.get().clone().unwrap_or_default()
+    }
+
+    fn list_guardians(&self) -> Vec<PublicKey> {
+        self.guardians.iter().cloned().collect()
+    }
+
+    fn list_members(&self) -> Vec<AccountId> {
+        self.members.iter().cloned().collect()
+    }
+
+    fn list_signer_keys(&self) -> Vec<PublicKey> {
+        self.signer_keys.iter().cloned().collect()
+    }
+
+    fn are_signer_keys_complete(&self) -> bool {
+        self.signer_keys_complete
+    }
+
+    fn get_pause_info(&self) -> Option<PauseInfo> {
+        self.paused.clone()
+    }
+
+    fn get_recovery_config(&self) -> Option<RecoveryConfig> {
+        self.recovery.clone()
+    }
+
+    fn get_recovery_proposal(&self) -> Option<RecoveryProposal> {
+        self.recovery_proposal.clone()
+    }
+
+    fn get_last_activity(&self, member: Member) -> Option<u64> {
+        self.last_activity.get(&member).copied()
+    }
+
+    fn list_schedule_ids(&self) -> Vec<ScheduleId> {
+        self.schedules.keys().copied().collect()
+    }
+
+    fn get_schedule(&self, schedule_id: ScheduleId) -> Option<ScheduledRequest> {
+        self.schedules.get(&schedule_id).cloned()
+    }
+
+    fn list_stream_ids(&self) -> Vec<StreamId> {
+        self.streams.keys().copied().collect()
+    }
+
+    fn get_stream(&self, stream_id: StreamId) -> Option<Stream> {
+        self.streams.get(&stream_id).cloned()
+    }
+
+    fn get_claimable(&self, stream_id: StreamId) -> U128 {
+        self.claimable_amount(stream_id).into()
+    }
 }
 
 impl MultiSigContract {
@@ -148,7 +697,35 @@ impl MultiSigContract {
     Helper methods
     ********************************/
 
-    fn execute_request(&mut self, request: MultiSigRequest) -> PromiseOrValue<()> {
+    // executes the request and tracks its outcome for retries and the requests depending on it
+    fn execute_request(&mut self, request_id: MultisigRequestId, executing: ExecutingRequest) -> PromiseOrValue<()> {
+        let request = executing.pending_request();
+        let has_callback = matches!(
+            request.actions.as_slice(),
+            [MultiSigRequestAction::UpgradeSelf { .. } | MultiSigRequestAction::Batch { .. }]
+        );
+        match self.execute_actions(request_id, request) {
+            PromiseOrValue::Value(()) => {
+                self.finish_request(request_id, RequestOutcome::Succeeded);
+                PromiseOrValue::Value(())
+            }
+            PromiseOrValue::Promise(promise) => {
+                // kept until the outcome is known so a failed request can be retried
+                self.executing.insert(request_id, executing);
+                if has_callback {
+                    // on_upgrade_self and on_batch_executed report the outcome themselves
+                    promise.into()
+                } else {
+                    track_execution(promise, request_id).into()
+                }
+            }
+        }
+    }
+
+    fn execute_actions(&mut self, request_id: MultisigRequestId, request: MultiSigRequest) -> PromiseOrValue<()> {
+        // the policy might have changed since the request was added
+        self.assert_allowed_by_policy(&request);
+        self.assert_not_paused(&request);
         let mut promise = Promise::new(request.receiver_id.clone());
         let receiver_id = request.receiver_id.clone();
         let num_actions = request.actions.len();
@@ -156,43 +733,48 @@ impl MultiSigContract {
             promise = match action {
                 MultiSigRequestAction::Transfer { amount } => promise.transfer(amount),
                 MultiSigRequestAction::CreateAccount => promise.create_account(),
-                MultiSigRequestAction::DeployContract { code } => promise.deploy_contract(code.into()),
+                MultiSigRequestAction::DeployContract { code } => promise.deploy_contract(code.0),
+                MultiSigRequestAction::DeployStagedCode { code_hash } => {
+                    promise.deploy_contract(self.staged_code_bytes(code_hash.into()))
+                }
                 MultiSigRequestAction::AddKey { public_key, permission } => {
                     assert_self_request(receiver_id.clone());
+                    self.revoked_keys.remove(&public_key);
+                    // keys that can call the multisig must confirm full access keys as well
+                    let can_sign = match &permission {
+                        Some(permission) => permission.receiver_id == env::current_account_id(),
+                        None => true,
+                    };
+                    if can_sign {
+                        self.signer_keys.insert(public_key.clone());
+                        self.set_key_permission(&public_key, permission.clone());
+                        self.invalidate_confirmations(None);
+                    }
                     if let Some(permission) = permission {
-                        // TODO:
-                        #[allow(deprecated)]
-                        promise.add_access_key(
-                            public_key,
-                            permission
-                                .allowance
-                                .map_or(DEFAULT_ALLOWANCE, |allowance| NearToken::from_yoctonear(allowance.0)),
-                            permission.receiver_id,
-                            permission.method_names.join(","),
-                        )
+                        add_function_call_key(promise, public_key, permission)
                     } else {
                         // wallet UI should warn user if receiver_id == env::current_account_id(), adding FAK will render multisig useless
+                        self.assert_signer_keys_complete();
                         promise.add_full_access_key(public_key)
                     }
                 }
                 MultiSigRequestAction::DeleteKey { public_key } => {
                     assert_self_request(receiver_id.clone());
-                    let pk: PublicKey = public_key;
-                    // delete outstanding requests by public_key
-                    let request_ids: Vec<u32> = self
-                        .requests
-                        .iter()
-                        .filter(|(_k, r)| r.signer_pk == pk)
-                        .map(|(k, _r)| k)
-                        .collect();
-                    for request_id in request_ids {
-                        // remove confirmations for this request
-                        self.confirmations.remove(&request_id);
-                        self.requests.remove(&request_id);
-                    }
-                    // remove num_requests_pk entry for public_key
-                    self.num_requests_pk.remove(&pk);
-                    promise.delete_key(pk)
+                    self.remove_signer(&Member::Key(public_key.clone()));
+                    promise.delete_key(public_key)
+                }
+                MultiSigRequestAction::RotateKey { old, new } => {
+                    assert_self_request(receiver_id.clone());
+                    let permission = self.rotate_key(&old, &new);
+                    add_function_call_key(promise, new, permission).delete_key(old)
+                }
+                MultiSigRequestAction::AddSignerKey { public_key, allowance } => {
+                    assert_self_request(receiver_id.clone());
+                    self.revoked_keys.remove(&public_key);
+                    self.signer_keys.insert(public_key.clone());
+                    self.set_key_permission(&public_key, Some(signer_permission(allowance)));
+                    self.invalidate_confirmations(None);
+                    add_function_call_key(promise, public_key, signer_permission(allowance))
                 }
                 MultiSigRequestAction::FunctionCall {
                     method_name,
@@ -205,59 +787,239 @@ impl MultiSigContract {
                     env::log_str(&format!("gas: {gas:?}"));
                     env::log_str(&format!("args.0.len(): {}", args.0.len()));
 
-                    promise.function_call(method_name, args.into(), deposit, gas)
+                    promise.function_call(method_name, args.0, deposit, gas)
                 }
                 // the following methods must be a single action
-                MultiSigRequestAction::SetNumConfirmations { num_confirmations } => {
-                    assert_one_action_only(receiver_id, num_actions);
-                    self.num_confirmations = num_confirmations;
-                    return PromiseOrValue::Value(());
-                }
-                MultiSigRequestAction::SetActiveRequestsLimit { active_requests_limit } => {
+                action => {
                     assert_one_action_only(receiver_id, num_actions);
-                    self.active_requests_limit = active_requests_limit;
-                    return PromiseOrValue::Value(());
+                    return self.execute_single_action(request_id, promise, action);
                 }
             };
         }
         promise.into()
     }
 
-    // removes request, removes confirmations and reduces num_requests_pk - used in delete, delete_key, and confirm
+    // Executes an action that must be the only one of its request - used in execute_actions
+    fn execute_single_action(
+        &mut self,
+        request_id: MultisigRequestId,
+        promise: Promise,
+        action: MultiSigRequestAction,
+    ) -> PromiseOrValue<()> {
+        match action {
+            MultiSigRequestAction::RegisterSignerKeys { keys } => {
+                for SignerKey { public_key, permission } in keys {
+                    // deleted keys can't sign anymore
+                    assert!(
+                        !self.revoked_keys.contains(&public_key),
+                        "Key {public_key:?} was deleted from the multisig"
+                    );
+                    self.signer_keys.insert(public_key.clone());
+                    self.set_key_permission(&public_key, Some(permission));
+                }
+                self.signer_keys_complete = true;
+                PromiseOrValue::Value(())
+            }
+            action @ (MultiSigRequestAction::SetNumConfirmations { .. }
+            | MultiSigRequestAction::SetActiveRequestsLimit { .. }
+            | MultiSigRequestAction::SetElevatedNumConfirmations { .. }
+            | MultiSigRequestAction::SetPolicy { .. }
+            | MultiSigRequestAction::SetRetryConfig { .. }
+            | MultiSigRequestAction::SetRequestCooldown { .. }
+            | MultiSigRequestAction::SetSpendingLimit { .. }
+            | MultiSigRequestAction::SetGuardians { .. }
+            | MultiSigRequestAction::SetRecovery { .. }
+            | MultiSigRequestAction::UpdateConfig { .. }) => {
+                self.update_config(config_update(action));
+                PromiseOrValue::Value(())
+            }
+            MultiSigRequestAction::Unpause { guardians } => {
+                if guardians.is_some() {
+                    self.update_config(ConfigUpdate {
+                        guardians,
+                        ..Default::default()
+                    });
+                }
+                self.paused = None;
+                MultisigEvent::Unpaused.emit();
+                PromiseOrValue::Value(())
+            }
+            MultiSigRequestAction::Schedule {
+                request,
+                start,
+                interval,
+                count,
+            } => {
+                self.add_schedule(
+                    request_id,
+                    ScheduledRequest {
+                        request,
+                        start,
+                        interval,
+                        remaining: count,
+                        history: vec![],
+                    },
+                );
+                PromiseOrValue::Value(())
+            }
+            MultiSigRequestAction::CancelSchedule { schedule_id } => {
+                self.schedules.remove(&schedule_id).expect("No such schedule");
+                PromiseOrValue::Value(())
+            }
+            MultiSigRequestAction::CreateStream {
+                token_id,
+                receiver_id,
+                start,
+                cliff,
+                end,
+                total,
+            } => {
+                self.add_stream(
+                    request_id,
+                    Stream {
+                        token_id,
+                        receiver_id,
+                        start,
+                        cliff,
+                        end,
+                        total,
+                        claimed: U128(0),
+                        pending_claims: 0,
+                    },
+                );
+                PromiseOrValue::Value(())
+            }
+            MultiSigRequestAction::RevokeStream { stream_id } => {
+                self.revoke_stream(stream_id);
+                PromiseOrValue::Value(())
+            }
+            MultiSigRequestAction::Batch { transactions } => execute_batch(request_id, &transactions).into(),
+            MultiSigRequestAction::AddMember { account_id } => {
+                self.add_member(account_id);
+                PromiseOrValue::Value(())
+            }
+            MultiSigRequestAction::RemoveMember { account_id } => {
+                self.remove_member(account_id);
+                PromiseOrValue::Value(())
+            }
+            MultiSigRequestAction::UpgradeSelf { code, migrate_args } => {
+                upgrade_self(promise, request_id, code.into(), migrate_args.into()).into()
+            }
+            _ => unreachable!("Not a single action"),
+        }
+    }
+
+    // number of confirmations required to execute the request
+    fn required_confirmations(&self, request: &MultiSigRequest) -> usize {
+        // a full access key can do anything on behalf of the multisig, so all members must agree
+        if is_unanimous(request) {
+            return self.num_members().max(self.get_elevated_num_confirmations());
+        }
+        let is_self = request.receiver_id == env::current_account_id();
+        let is_elevated = request.actions.iter().any(|action| match action {
+            MultiSigRequestAction::UpgradeSelf { .. }
+            | MultiSigRequestAction::SetPolicy { .. }
+            | MultiSigRequestAction::SetRecovery { .. }
+            | MultiSigRequestAction::SetElevatedNumConfirmations { .. }
+            | MultiSigRequestAction::RegisterSignerKeys { .. } => true,
+            MultiSigRequestAction::UpdateConfig { update } => {
+                update.policy.is_some() || update.elevated_num_confirmations.is_some() || update.recovery.is_some()
+            }
+            // replaces the code of the multisig like UpgradeSelf does
+            MultiSigRequestAction::DeployContract { .. } | MultiSigRequestAction::DeployStagedCode { .. } => is_self,
+            _ => false,
+        });
+        if is_elevated {
+            self.get_elevated_num_confirmations()
+        } else {
+            self.num_confirmations
+        }
+    }
+
+    // removes the request without execution along with the requests depending on it - used in delete and delete_key
+    fn cancel_request(&mut self, request_id: MultisigRequestId) -> MultiSigRequest {
+        let request = self.remove_request(request_id);
+        self.unstage_request_code(&request);
+        self.cancel_dependents(request_id);
+        request
+    }
+    // removes request, removes confirmations and removes it from signer's requests - used in delete, delete_key, and confirm
     fn remove_request(&mut self, request_id: MultisigRequestId) -> MultiSigRequest {
         // remove confirmations for this request
-        self.confirmations.remove(&request_id);
+        self.remove_confirmations(request_id);
         // remove the original request
         let request_with_signer = self
             .requests
             .remove(&request_id)
             .expect("Failed to remove existing element");
         // decrement num_requests for original request signer
-        let original_signer_pk = request_with_signer.signer_pk;
-        let mut num_requests = self.num_requests_pk.get(&original_signer_pk).unwrap_or(0);
-        num_requests = num_requests.saturating_sub(1);
-        self.num_requests_pk.insert(&original_signer_pk, &num_requests);
+        remove_member_request(&mut self.requests_by_signer, &request_with_signer.signer, request_id);
+        self.dependencies.remove(&request_id);
+        self.remove_delete_votes(request_id);
         // return request
         request_with_signer.request
     }
+    // clears the confirmation set of the request before dropping it, otherwise its entries stay in storage
+    fn remove_confirmations(&mut self, request_id: MultisigRequestId) {
+        if let Some(mut confirmations) = self.confirmations.remove(&request_id) {
+            for member in confirmations.iter() {
+                remove_member_request(&mut self.confirmed_requests, member, request_id);
+            }
+            confirmations.clear();
+        }
+    }
+    // deletes pending requests of the member and its confirmations - used in delete_key, remove_member and recovery
+    fn remove_signer(&mut self, member: &Member) {
+        for request_id in self.list_requests_by_signer(member.clone()) {
+            // might have been cancelled already as a dependent of another request
+            if self.requests.contains_key(&request_id) {
+                self.cancel_request(request_id);
+            }
+        }
+        self.revoke_delete_votes(member);
+        self.invalidate_confirmations(Some(member));
+        self.last_activity.remove(member);
+        if let Member::Key(public_key) = member {
+            self.revoked_keys.insert(public_key.clone());
+            self.signer_keys.remove(public_key);
+            self.key_permissions.remove(public_key);
+        }
+    }
+    // removes confirmations of the member from all pending requests - used in invalidate_confirmations
+    fn revoke_confirmations(&mut self, member: &Member) {
+        let Some(mut confirmed_requests) = self.confirmed_requests.remove(member) else {
+            return;
+        };
+        let request_ids: Vec<MultisigRequestId> = confirmed_requests.iter().copied().collect();
+        confirmed_requests.clear();
+        for request_id in &request_ids {
+            if let Some(confirmations) = self.confirmations.get_mut(request_id) {
+                confirmations.remove(member);
+            }
+        }
+        if !request_ids.is_empty() {
+            MultisigEvent::ConfirmationsRevoked {
+                member: member.clone(),
+                request_ids,
+            }
+            .emit();
+        }
+    }
     // Prevents access to calling requests and make sure request_id is valid - used in delete and confirm
-    fn assert_valid_request(&mut self, request_id: MultisigRequestId) {
-        // request must come from key added to contract account
-        assert_eq!(
-            env::current_account_id(),
-            env::predecessor_account_id(),
-            "Predecessor account must much current account"
-        );
+    fn assert_valid_request(&mut self, request_id: MultisigRequestId) -> Member {
+        // request must come from key added to contract account or from a member account
+        let member = self.assert_member();
         // request must exist
         assert!(
-            self.requests.get(&request_id).is_some(),
+            self.requests.contains_key(&request_id),
             "No such request: either wrong number or already confirmed"
         );
         // request must have
         assert!(
-            self.confirmations.get(&request_id).is_some(),
+            self.confirmations.contains_key(&request_id),
             "Internal error: confirmations mismatch requests"
         );
+        member
     }
 }
 
@@ -274,14 +1036,114 @@ fn assert_one_action_only(receiver_id: AccountId, num_actions: usize) {
     assert_self_request(receiver_id);
     assert_eq!(num_actions, 1, "This method should be a separate request");
 }
+// Adds the request to the set of requests of the member, creating the set if needed
+pub(crate) fn insert_member_request(
+    index: &mut LookupMap<Member, IterableSet<MultisigRequestId>>,
+    member: Member,
+    request_id: MultisigRequestId,
+    prefix: StorageKey,
+) {
+    index
+        .entry(member)
+        .or_insert_with(|| IterableSet::new(prefix))
+        .insert(request_id);
+}
+// Removes the request from the set of requests of the member, dropping the set once it's empty
+pub(crate) fn remove_member_request(
+    index: &mut LookupMap<Member, IterableSet<MultisigRequestId>>,
+    member: &Member,
+    request_id: MultisigRequestId,
+) {
+    if let Some(request_ids) = index.get_mut(member) {
+        request_ids.remove(&request_id);
+        if request_ids.is_empty() {
+            index.remove(member);
+        }
+    }
+}
+// Whether the request adds a full access key to the multisig - used for unanimous requests
+pub(crate) fn is_unanimous(request: &MultiSigRequest) -> bool {
+    request.receiver_id == env::current_account_id()
+        && request
+            .actions
+            .iter()
+            .any(|action| matches!(action, MultiSigRequestAction::AddKey { permission: None, .. }))
+}
+// Rejects requests that can never be executed - used in add_request
+fn assert_valid_actions(request: &MultiSigRequest) {
+    for action in &request.actions {
+        match action {
+            MultiSigRequestAction::UpgradeSelf { .. } => {
+                assert_one_action_only(request.receiver_id.clone(), request.actions.len());
+            }
+            MultiSigRequestAction::SetRecovery { recovery } => {
+                assert_one_action_only(request.receiver_id.clone(), request.actions.len());
+                if let Some(recovery) = recovery {
+                    assert_valid_recovery(recovery);
+                }
+            }
+            MultiSigRequestAction::SetPolicy { policy } => {
+                assert_one_action_only(request.receiver_id.clone(), request.actions.len());
+                assert_valid_policy(policy);
+            }
+            MultiSigRequestAction::UpdateConfig { update } => {
+                assert_one_action_only(request.receiver_id.clone(), request.actions.len());
+                if let Some(policy) = &update.policy {
+                    assert_valid_policy(policy);
+                }
+                if let Some(Some(recovery)) = &update.recovery {
+                    assert_valid_recovery(recovery);
+                }
+            }
+            MultiSigRequestAction::Schedule {
+                request: scheduled,
+                interval,
+                count,
+                ..
+            } => {
+                assert_one_action_only(request.receiver_id.clone(), request.actions.len());
+                assert_valid_schedule(scheduled, interval.0, *count);
+            }
+            MultiSigRequestAction::CreateStream {
+                start,
+                cliff,
+                end,
+                total,
+                ..
+            } => {
+                assert_one_action_only(request.receiver_id.clone(), request.actions.len());
+                assert_valid_stream(start.0, cliff.0, end.0, total.0);
+            }
+            MultiSigRequestAction::RegisterSignerKeys { keys } => {
+                assert_one_action_only(request.receiver_id.clone(), request.actions.len());
+                for key in keys {
+                    assert_eq!(
+                        key.permission.receiver_id,
+                        env::current_account_id(),
+                        "Signer keys must be restricted to the multisig"
+                    );
+                }
+            }
+            MultiSigRequestAction::Batch { transactions } => {
+                assert_one_action_only(request.receiver_id.clone(), request.actions.len());
+                assert!(!transactions.is_empty(), "Batch must have transactions");
+                for transaction in transactions {
+                    assert_valid_transaction(transaction, "Only Transfer and FunctionCall can be batched");
+                }
+            }
+            _ => {}
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
+    use multisig_model::{MultiSigRequestActionKind, ScheduledRun, TokenSpendingLimit};
     use near_sdk::{
-        test_utils::test_env::{alice, bob},
-        testing_env, AccountId, BlockHeight, EpochHeight, Gas, VMContext,
+        test_utils::test_env::{alice, bob, carol},
+        testing_env, AccountId, BlockHeight, EpochHeight, Gas, NearToken, PromiseResult, VMContext,
     };
 
     use super::*;
@@ -298,7 +1160,7 @@ mod tests {
                     signer_account_id: AccountId::from_str("signer_account_id").unwrap(),
                     signer_account_pk: PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
                     predecessor_account_id: AccountId::from_str("predecessor_account_id").unwrap(),
-                    input: vec![],
+                    input: Vec::new().into(),
                     epoch_height: 0,
                     block_index: 0,
                     block_timestamp: 0,
@@ -397,6 +1259,36 @@ mod tests {
             .finish()
     }
 
+    fn context_with_key_at(key: PublicKey, amount: NearToken, timestamp: u64) -> VMContext {
+        VMContextBuilder::new()
+            .current_account_id(alice())
+            .block_timestamp(timestamp)
+            .predecessor_account_id(alice())
+            .signer_account_id(alice())
+            .signer_account_pk(key)
+            .account_balance(amount)
+            .finish()
+    }
+
+    // Multisig of the test keys where `request` was added by the first key and confirmed by `num_confirmations` keys,
+    // the context is left on the last key that confirmed it
+    fn contract_with_request(num_confirmations: usize, request: MultiSigRequest) -> MultiSigContract {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_near(1)
+        ));
+        let mut c = MultiSigContract::new(num_confirmations);
+        let request_id = c.add_request_and_confirm(request);
+        if num_confirmations > 1 {
+            testing_env!(context_with_key(
+                PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+                NearToken::from_near(1)
+            ));
+            c.confirm(request_id);
+        }
+        c
+    }
+
     #[test]
     fn test_multi_3_of_n() {
         let amount = NearToken::from_yoctonear(1_000);
@@ -408,6 +1300,7 @@ mod tests {
         let request = MultiSigRequest {
             receiver_id: bob(),
             actions: vec![MultiSigRequestAction::Transfer { amount: amount.into() }],
+            depends_on: vec![],
         };
         let request_id = c.add_request(request.clone());
         assert_eq!(c.get_request(request_id), request);
@@ -442,6 +1335,7 @@ mod tests {
         let request = MultiSigRequest {
             receiver_id: bob(),
             actions: vec![MultiSigRequestAction::Transfer { amount: amount.into() }],
+            depends_on: vec![],
         };
         let request_id = c.add_request_and_confirm(request.clone());
         assert_eq!(c.get_request(request_id), request);
@@ -472,7 +1366,9 @@ mod tests {
             PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
             amount
         ));
-        let mut c = MultiSigContract::new(1);
+        // a full access key can only be added once all signer keys are known
+        let mut c =
+            MultiSigContract::new_with_config(vec![Member::Key(env::signer_account_pk())], 1, None, None, None, None);
         let new_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
         // vm current_account_id is alice, receiver_id must be alice
         let request = MultiSigRequest {
@@ -481,6 +1377,7 @@ mod tests {
                 public_key: new_key.clone(),
                 permission: None,
             }],
+            depends_on: vec![],
         };
         // make request
         c.add_request_and_confirm(request.clone());
@@ -494,6 +1391,7 @@ mod tests {
         let request2 = MultiSigRequest {
             receiver_id: alice(),
             actions: vec![MultiSigRequestAction::Transfer { amount: amount.into() }],
+            depends_on: vec![],
         };
         // make request but don't confirm
         c.add_request(request2.clone());
@@ -506,6 +1404,7 @@ mod tests {
             actions: vec![MultiSigRequestAction::DeleteKey {
                 public_key: new_key.clone(),
             }],
+            depends_on: vec![],
         };
         // make request and confirm
         c.add_request_and_confirm(request3.clone());
@@ -531,6 +1430,7 @@ mod tests {
                 public_key: new_key.clone(),
                 permission: None,
             }],
+            depends_on: vec![],
         };
         // make request
         c.add_request_and_confirm(request);
@@ -547,6 +1447,7 @@ mod tests {
         let request_id = c.add_request(MultiSigRequest {
             receiver_id: alice(),
             actions: vec![MultiSigRequestAction::SetNumConfirmations { num_confirmations: 2 }],
+            depends_on: vec![],
         });
         c.confirm(request_id);
         assert_eq!(c.num_confirmations, 2);
@@ -564,6 +1465,7 @@ mod tests {
         let request_id = c.add_request(MultiSigRequest {
             receiver_id: bob(),
             actions: vec![MultiSigRequestAction::Transfer { amount: amount.into() }],
+            depends_on: vec![],
         });
         assert_eq!(c.requests.len(), 1);
         assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 0);
@@ -573,7 +1475,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "Request cannot be deleted immediately after creation.")]
     fn test_panics_delete_request() {
         let amount = NearToken::from_yoctonear(1_000);
         testing_env!(context_with_key(
@@ -584,10 +1486,15 @@ mod tests {
         let request_id = c.add_request(MultiSigRequest {
             receiver_id: bob(),
             actions: vec![MultiSigRequestAction::Transfer { amount: amount.into() }],
+            depends_on: vec![],
         });
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            amount
+        ));
         c.delete_request(request_id);
         assert_eq!(c.requests.len(), 0);
-        assert_eq!(c.confirmations.len(), 0);
+        assert!(!c.confirmations.contains_key(&request_id));
     }
 
     #[test]
@@ -601,6 +1508,7 @@ mod tests {
         let request_id = c.add_request(MultiSigRequest {
             receiver_id: bob(),
             actions: vec![MultiSigRequestAction::Transfer { amount: amount.into() }],
+            depends_on: vec![],
         });
         testing_env!(context_with_key_future(
             PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
@@ -608,7 +1516,7 @@ mod tests {
         ));
         c.delete_request(request_id);
         assert_eq!(c.requests.len(), 0);
-        assert_eq!(c.confirmations.len(), 0);
+        assert!(!c.confirmations.contains_key(&request_id));
     }
 
     #[test]
@@ -623,28 +1531,2420 @@ mod tests {
         let request_id = c.add_request(MultiSigRequest {
             receiver_id: bob(),
             actions: vec![MultiSigRequestAction::Transfer { amount: amount.into() }],
+            depends_on: vec![],
         });
-        testing_env!(context_with_key(
-            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
-            amount
-        ));
+        testing_env!(context_for_member(carol()));
         c.delete_request(request_id);
     }
 
     #[test]
-    #[should_panic]
-    fn test_too_many_requests() {
+    fn test_author_withdraws_request() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), amount));
+        let mut c = MultiSigContract::new(3);
+        let request_id = c.add_request(transfer_request(vec![]));
+        c.delete_request(request_id);
+        assert!(c.list_request_ids().is_empty());
+        assert_eq!(
+            c.list_history(0, 10),
+            vec![RequestRecord {
+                request_id,
+                outcome: RequestOutcome::Deleted {
+                    deleted_by: vec![Member::Key(key)]
+                },
+                timestamp: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_delete_votes_of_removed_key_dropped() {
         let amount = NearToken::from_yoctonear(1_000);
         testing_env!(context_with_key(
             PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
             amount
         ));
-        let mut c = MultiSigContract::new(3);
-        for _i in 0..16 {
+        let mut c = MultiSigContract::new(2);
+        let request_id = c.add_request(transfer_request(vec![]));
+
+        let other_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        testing_env!(context_with_key_future(other_key.clone(), amount));
+        c.delete_request(request_id);
+        let delete_key_id = c.add_request_and_confirm(member_request(MultiSigRequestAction::DeleteKey {
+            public_key: other_key.clone(),
+        }));
+        let third_key = PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap();
+        testing_env!(context_with_key_future(third_key, amount));
+        c.confirm(delete_key_id);
+        assert!(c.get_delete_votes(request_id).is_empty());
+        assert!(c.delete_voted_requests.get(&Member::Key(other_key)).is_none());
+        assert_eq!(c.list_request_ids(), vec![request_id]);
+    }
+
+    #[test]
+    fn test_delete_request_quorum() {
+        let amount = NearToken::from_yoctonear(1_000);
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(2);
+        let request_id = c.add_request(transfer_request(vec![]));
+
+        let other_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        testing_env!(context_with_key_future(other_key.clone(), amount));
+        assert_eq!(c.delete_request(request_id), DeleteOutcome::Voted { votes: 1 });
+        assert_eq!(c.list_request_ids(), vec![request_id]);
+        assert_eq!(c.get_delete_votes(request_id), vec![Member::Key(other_key.clone())]);
+
+        let third_key = PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap();
+        testing_env!(context_with_key_future(third_key.clone(), amount));
+        assert_eq!(
+            c.delete_request(request_id),
+            DeleteOutcome::Deleted {
+                request: transfer_request(vec![])
+            }
+        );
+        assert!(c.list_request_ids().is_empty());
+        assert_eq!(
+            c.list_history(0, 10)[0].outcome,
+            RequestOutcome::Deleted {
+                deleted_by: vec![Member::Key(other_key), Member::Key(third_key)]
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_too_many_requests() {
+        let amount = NearToken::from_yoctonear(1_000);
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(3);
+        for _i in 0..16 {
             c.add_request(MultiSigRequest {
                 receiver_id: bob(),
                 actions: vec![MultiSigRequestAction::Transfer { amount: amount.into() }],
+                depends_on: vec![],
             });
         }
     }
+
+    #[test]
+    #[should_panic(expected = "This method should be a separate request")]
+    fn test_panics_upgrade_self_with_other_actions() {
+        let amount = NearToken::from_yoctonear(1_000);
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(1);
+        c.add_request(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![
+                MultiSigRequestAction::UpgradeSelf {
+                    code: vec![1, 2, 3].into(),
+                    migrate_args: vec![].into(),
+                },
+                MultiSigRequestAction::Transfer { amount: amount.into() },
+            ],
+            depends_on: vec![],
+        });
+    }
+
+    #[test]
+    fn test_upgrade_self_requires_elevated_confirmations() {
+        let amount = NearToken::from_yoctonear(1_000);
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(1);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::AddSignerKey {
+            public_key: PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            allowance: None,
+        }));
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetElevatedNumConfirmations { num_confirmations: 2 }],
+            depends_on: vec![],
+        });
+        assert_eq!(c.get_elevated_num_confirmations(), 2);
+
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::UpgradeSelf {
+                code: vec![1, 2, 3].into(),
+                migrate_args: vec![].into(),
+            }],
+            depends_on: vec![],
+        });
+        assert_eq!(c.requests.len(), 1);
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            amount
+        ));
+        c.confirm(request_id);
+        assert_eq!(c.requests.len(), 0);
+    }
+
+    #[test]
+    fn test_elevated_threshold_and_self_deploy_require_elevated_confirmations() {
+        let amount = NearToken::from_yoctonear(1_000);
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(1);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::AddSignerKey {
+            public_key: PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            allowance: None,
+        }));
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetElevatedNumConfirmations { num_confirmations: 2 }],
+            depends_on: vec![],
+        });
+
+        let lower_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::UpdateConfig {
+                update: Box::new(ConfigUpdate {
+                    elevated_num_confirmations: Some(1),
+                    ..Default::default()
+                }),
+            }],
+            depends_on: vec![],
+        });
+        let deploy_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::DeployContract {
+                code: vec![1, 2, 3].into(),
+            }],
+            depends_on: vec![],
+        });
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::DeployContract {
+                code: vec![1, 2, 3].into(),
+            }],
+            depends_on: vec![],
+        });
+        assert_eq!(c.requests.len(), 2);
+        assert_eq!(c.get_elevated_num_confirmations(), 2);
+
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            amount
+        ));
+        c.confirm(deploy_id);
+        c.confirm(lower_id);
+        assert_eq!(c.requests.len(), 0);
+        assert_eq!(c.get_elevated_num_confirmations(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Elevated number of confirmations must be between the number of confirmations")]
+    fn test_panics_elevated_threshold_above_members() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(1);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::UpdateConfig {
+            update: Box::new(ConfigUpdate {
+                elevated_num_confirmations: Some(2),
+                ..ConfigUpdate::default()
+            }),
+        }));
+    }
+
+    #[test]
+    fn test_on_upgrade_self_records_upgrade() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), amount));
+        let mut c = MultiSigContract::new(1);
+
+        testing_env!(
+            context_with_key(key.clone(), amount),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        c.on_upgrade_self(0, [1; 32].into());
+        assert_eq!(c.get_code_hash(), None);
+
+        testing_env!(
+            context_with_key(key, amount),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        c.on_upgrade_self(1, [1; 32].into());
+        c.on_upgrade_self(2, [2; 32].into());
+        assert_eq!(c.get_code_hash(), Some([2; 32].into()));
+
+        let upgrades = c.list_upgrades();
+        assert_eq!(upgrades.len(), 2);
+        assert_eq!(upgrades[0].old_code_hash, None);
+        assert_eq!(upgrades[1].request_id, 2);
+        assert_eq!(upgrades[1].old_code_hash, Some([1; 32].into()));
+    }
+
+    #[test]
+    fn test_retry_failed_upgrade_self() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), amount));
+        let mut c = MultiSigContract::new(1);
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::UpgradeSelf {
+                code: vec![1, 2, 3].into(),
+                migrate_args: vec![].into(),
+            }],
+            depends_on: vec![],
+        });
+        set_promise_result(PromiseResult::Failed);
+        c.on_upgrade_self(request_id, env::sha256_array(&[1, 2, 3]).into());
+        assert_eq!(c.get_failed_request(request_id).unwrap().attempts, 1);
+
+        testing_env!(context_with_key(key, amount));
+        c.retry(request_id, None);
+        set_promise_result(PromiseResult::Successful(vec![]));
+        c.on_upgrade_self(request_id, env::sha256_array(&[1, 2, 3]).into());
+        assert_eq!(c.get_failed_request(request_id), None);
+        assert_eq!(c.get_code_hash(), Some(env::sha256_array(&[1, 2, 3]).into()));
+    }
+
+    #[test]
+    fn test_deploy_staged_code() {
+        let amount = NearToken::from_yoctonear(1_000);
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(1);
+        let code_hash: Base58CryptoHash = env::sha256_array(&[1, 2, 3, 4]).into();
+        assert_eq!(c.stage_code(code_hash, U64(4), vec![1, 2].into()), U64(2));
+        assert_eq!(
+            c.get_staged_code(code_hash),
+            Some(StagedCodeInfo {
+                code_size: 4,
+                size: 2,
+                complete: false
+            })
+        );
+        assert_eq!(c.stage_code(code_hash, U64(4), vec![3, 4].into()), U64(4));
+        assert!(c.get_staged_code(code_hash).unwrap().complete);
+
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::DeployStagedCode { code_hash }],
+            depends_on: vec![],
+        });
+        assert_eq!(c.requests.len(), 0);
+        // kept until the deployment succeeds
+        assert!(c.get_staged_code(code_hash).is_some());
+        set_promise_result(PromiseResult::Successful(vec![]));
+        c.on_request_executed(request_id);
+        assert_eq!(c.get_staged_code(code_hash), None);
+    }
+
+    #[test]
+    fn test_retry_failed_deploy_of_staged_code() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), amount));
+        let mut c = MultiSigContract::new(1);
+        let code_hash: Base58CryptoHash = env::sha256_array(&[1, 2, 3, 4]).into();
+        c.stage_code(code_hash, U64(4), vec![1, 2, 3, 4].into());
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::DeployStagedCode { code_hash }],
+            depends_on: vec![],
+        });
+        set_promise_result(PromiseResult::Failed);
+        c.on_request_executed(request_id);
+        assert!(c.get_failed_request(request_id).is_some());
+        assert!(c.get_staged_code(code_hash).is_some());
+
+        testing_env!(context_with_key(key, amount));
+        c.retry(request_id, None);
+        set_promise_result(PromiseResult::Successful(vec![]));
+        c.on_request_executed(request_id);
+        assert_eq!(c.get_staged_code(code_hash), None);
+    }
+
+    #[test]
+    fn test_delete_failed_deploy_frees_staged_code() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), amount));
+        let mut c = MultiSigContract::new(1);
+        let code_hash: Base58CryptoHash = env::sha256_array(&[1, 2, 3, 4]).into();
+        c.stage_code(code_hash, U64(4), vec![1, 2, 3, 4].into());
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::DeployStagedCode { code_hash }],
+            depends_on: vec![],
+        });
+        set_promise_result(PromiseResult::Failed);
+        c.on_request_executed(request_id);
+
+        testing_env!(context_with_key(key, amount));
+        c.delete_request(request_id);
+        assert_eq!(c.get_staged_code(code_hash), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Code is already staged")]
+    fn test_panics_stage_chunk_after_complete_upload() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(1);
+        let code_hash: Base58CryptoHash = env::sha256_array(&[1, 2, 3, 4]).into();
+        c.stage_code(code_hash, U64(4), vec![1, 2, 3, 4].into());
+        c.stage_code(code_hash, U64(4), vec![5].into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Code is being staged by another member")]
+    fn test_panics_stage_chunk_of_other_member() {
+        let amount = NearToken::from_yoctonear(1_000);
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(1);
+        let code_hash: Base58CryptoHash = env::sha256_array(&[1, 2, 3, 4]).into();
+        c.stage_code(code_hash, U64(4), vec![1, 2].into());
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            amount
+        ));
+        c.stage_code(code_hash, U64(4), vec![3, 4].into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the uploader can unstage the code")]
+    fn test_panics_unstage_code_of_other_member() {
+        let amount = NearToken::from_yoctonear(1_000);
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(1);
+        let code_hash: Base58CryptoHash = env::sha256_array(&[1, 2, 3, 4]).into();
+        c.stage_code(code_hash, U64(4), vec![1, 2].into());
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            amount
+        ));
+        c.unstage_code(code_hash);
+    }
+
+    #[test]
+    #[should_panic(expected = "Staged code is incomplete")]
+    fn test_panics_deploy_incomplete_staged_code() {
+        let amount = NearToken::from_yoctonear(1_000);
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(1);
+        let code_hash: Base58CryptoHash = env::sha256_array(&[1, 2, 3, 4]).into();
+        c.stage_code(code_hash, U64(4), vec![1, 2].into());
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::DeployStagedCode { code_hash }],
+            depends_on: vec![],
+        });
+    }
+
+    #[test]
+    fn test_delete_request_frees_staged_code() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), amount));
+        let mut c = MultiSigContract::new(2);
+        let code_hash: Base58CryptoHash = env::sha256_array(&[1, 2, 3, 4]).into();
+        c.stage_code(code_hash, U64(4), vec![1, 2, 3, 4].into());
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::DeployStagedCode { code_hash }],
+            depends_on: vec![],
+        });
+        testing_env!(context_with_key_future(key, amount));
+        c.delete_request(request_id);
+        assert_eq!(c.get_staged_code(code_hash), None);
+    }
+
+    #[test]
+    fn test_staged_code_shared_by_requests() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), amount));
+        let mut c = MultiSigContract::new(2);
+        let code_hash: Base58CryptoHash = env::sha256_array(&[1, 2, 3, 4]).into();
+        c.stage_code(code_hash, U64(4), vec![1, 2, 3, 4].into());
+        let deploy_request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::DeployStagedCode { code_hash }],
+            depends_on: vec![],
+        };
+        let request_id = c.add_request(deploy_request.clone());
+        let other_id = c.add_request(deploy_request);
+
+        testing_env!(context_with_key_future(key, amount));
+        c.delete_request(request_id);
+        assert!(c.get_staged_code(code_hash).unwrap().complete);
+        c.confirm(other_id);
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            amount
+        ));
+        c.confirm(other_id);
+        assert!(c.list_request_ids().is_empty());
+        set_promise_result(PromiseResult::Successful(vec![]));
+        c.on_request_executed(other_id);
+        assert_eq!(c.get_staged_code(code_hash), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Staged code is used by pending requests")]
+    fn test_panics_unstage_code_in_use() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(2);
+        let code_hash: Base58CryptoHash = env::sha256_array(&[1, 2, 3, 4]).into();
+        c.stage_code(code_hash, U64(4), vec![1, 2, 3, 4].into());
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::DeployStagedCode { code_hash }],
+            depends_on: vec![],
+        });
+        c.unstage_code(code_hash);
+    }
+
+    #[test]
+    #[should_panic(expected = "Staged code exceeds the limit of 4194304 bytes")]
+    fn test_panics_stage_code_too_large() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(1);
+        c.stage_code([1; 32].into(), U64(4 * 1024 * 1024 + 1), vec![0; 1].into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Staged code doesn't match the hash")]
+    fn test_panics_stage_code_not_matching_hash() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(1);
+        let code_hash: Base58CryptoHash = env::sha256_array(&[1, 2, 3, 4]).into();
+        c.stage_code(code_hash, U64(4), vec![1, 2].into());
+        c.stage_code(code_hash, U64(4), vec![4, 3].into());
+    }
+
+    #[test]
+    fn test_stage_code_limit_per_member() {
+        let amount = NearToken::from_yoctonear(1_000);
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(1);
+        // abandoned uploads reserve the limit of their uploader only
+        let code_size = U64(4 * 1024 * 1024);
+        c.stage_code([1; 32].into(), code_size, vec![0].into());
+        c.stage_code([2; 32].into(), code_size, vec![0].into());
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            amount
+        ));
+        c.stage_code([3; 32].into(), code_size, vec![0].into());
+        assert_eq!(c.get_staged_code([3; 32].into()).unwrap().size, 1);
+
+        // unstaging frees the limit again
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            amount
+        ));
+        c.unstage_code([1; 32].into());
+        c.stage_code([4; 32].into(), code_size, vec![0].into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Code staged by the member exceeds the limit of 8388608 bytes")]
+    fn test_panics_stage_code_over_member_limit() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(1);
+        let code_size = U64(4 * 1024 * 1024);
+        c.stage_code([1; 32].into(), code_size, vec![0].into());
+        c.stage_code([2; 32].into(), code_size, vec![0].into());
+        c.stage_code([3; 32].into(), U64(1), vec![0].into());
+    }
+
+    #[test]
+    fn test_list_requests_by_signer() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let other_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        testing_env!(context_with_key(key.clone(), amount));
+        let mut c = MultiSigContract::new(2);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer { amount: amount.into() }],
+            depends_on: vec![],
+        };
+        let first_id = c.add_request(request.clone());
+        let second_id = c.add_request(request.clone());
+        testing_env!(context_with_key(other_key.clone(), amount));
+        let other_id = c.add_request(request);
+
+        let mut request_ids = c.list_requests_by_signer(Member::Key(key.clone()));
+        request_ids.sort_unstable();
+        assert_eq!(request_ids, vec![first_id, second_id]);
+        assert_eq!(
+            c.list_requests_by_signer(Member::Key(other_key.clone())),
+            vec![other_id]
+        );
+
+        // the other key removes the first one along with its requests
+        let delete_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::DeleteKey {
+                public_key: key.clone(),
+            }],
+            depends_on: vec![],
+        });
+        testing_env!(context_with_key(key.clone(), amount));
+        c.confirm(delete_id);
+
+        assert!(c.list_requests_by_signer(Member::Key(key.clone())).is_empty());
+        assert_eq!(c.get_num_requests_pk(key), 0);
+        assert_eq!(c.list_request_ids(), vec![other_id]);
+        assert_eq!(c.list_requests_by_signer(Member::Key(other_key)), vec![other_id]);
+    }
+
+    #[test]
+    fn test_delete_key_revokes_confirmations() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let second_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        let third_key = PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap();
+        testing_env!(context_with_key(second_key.clone(), amount));
+        let mut c = MultiSigContract::new(2);
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer { amount: amount.into() }],
+            depends_on: vec![],
+        });
+        testing_env!(context_with_key(key.clone(), amount));
+        c.confirm(request_id);
+        assert_eq!(c.get_confirmations(request_id), vec![Member::Key(key.clone())]);
+
+        testing_env!(context_with_key(second_key, amount));
+        let delete_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::DeleteKey {
+                public_key: key.clone(),
+            }],
+            depends_on: vec![],
+        });
+        testing_env!(context_with_key(third_key, amount));
+        c.confirm(delete_id);
+
+        assert!(c.get_confirmations(request_id).is_empty());
+        assert!(!c.confirmed_requests.contains_key(&Member::Key(key.clone())));
+        assert!(c.revoked_keys.contains(&key));
+    }
+
+    #[test]
+    fn test_get_revoked_confirmations() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), amount));
+        let mut c = MultiSigContract::new(2);
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer { amount: amount.into() }],
+            depends_on: vec![],
+        });
+        assert!(c.get_revoked_confirmations(request_id).is_empty());
+
+        // e.g. a confirmation kept from the state before the upgrade
+        c.revoked_keys.insert(key.clone());
+        assert_eq!(c.get_revoked_confirmations(request_id), vec![key]);
+    }
+
+    fn set_spending_limit(c: &mut MultiSigContract, token_id: Option<AccountId>, limit: SpendingLimit) {
+        let amount = NearToken::from_yoctonear(1_000);
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            amount
+        ));
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetSpendingLimit {
+                token_id,
+                limit: Some(limit),
+            }],
+            depends_on: vec![],
+        });
+        testing_env!(context_with_key(
+            PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap(),
+            amount
+        ));
+        c.confirm(request_id);
+    }
+
+    #[test]
+    fn test_transfer_within_spending_limit() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let period = 86_400_000_000_000;
+        testing_env!(context_with_key(key.clone(), amount));
+        let mut c = MultiSigContract::new(2);
+        set_spending_limit(
+            &mut c,
+            None,
+            SpendingLimit {
+                amount: 1_000.into(),
+                period: period.into(),
+                per_member: false,
+            },
+        );
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: NearToken::from_yoctonear(600),
+            }],
+            depends_on: vec![],
+        };
+
+        testing_env!(context_with_key(key.clone(), amount));
+        c.add_request_and_confirm(request.clone());
+        assert_eq!(c.requests.len(), 0);
+        assert_eq!(c.get_remaining_allowance(None, None), U128(400));
+
+        // above the limit, requires the normal number of confirmations
+        c.add_request_and_confirm(request.clone());
+        assert_eq!(c.requests.len(), 1);
+
+        // the allowance is restored in the next period
+        testing_env!(context_with_key_at(key, amount, period + 1));
+        assert_eq!(c.get_remaining_allowance(None, None), U128(1_000));
+        c.add_request_and_confirm(request);
+        assert_eq!(c.requests.len(), 1);
+        assert_eq!(c.get_remaining_allowance(None, None), U128(400));
+    }
+
+    #[test]
+    fn test_failed_transfer_refunds_allowance() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), amount));
+        let mut c = MultiSigContract::new(2);
+        set_spending_limit(
+            &mut c,
+            None,
+            SpendingLimit {
+                amount: 1_000.into(),
+                period: 86_400_000_000_000.into(),
+                per_member: false,
+            },
+        );
+        testing_env!(context_with_key(key, amount));
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: NearToken::from_yoctonear(600),
+            }],
+            depends_on: vec![],
+        });
+        assert_eq!(c.get_remaining_allowance(None, None), U128(400));
+
+        set_promise_result(PromiseResult::Failed);
+        c.on_request_executed(request_id);
+        assert!(c.get_failed_request(request_id).is_some());
+        assert_eq!(c.get_remaining_allowance(None, None), U128(1_000));
+    }
+
+    #[test]
+    fn test_ft_transfer_within_per_member_spending_limit() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let other_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        testing_env!(context_with_key(key.clone(), amount));
+        let mut c = MultiSigContract::new(2);
+        set_spending_limit(
+            &mut c,
+            Some(bob()),
+            SpendingLimit {
+                amount: 100.into(),
+                period: 86_400_000_000_000.into(),
+                per_member: true,
+            },
+        );
+        let ft_transfer = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::FunctionCall {
+                method_name: "ft_transfer".to_string(),
+                args: br#"{"receiver_id": "carol", "amount": "100"}"#.to_vec().into(),
+                deposit: NearToken::from_yoctonear(1),
+                gas: Gas::from_tgas(10),
+            }],
+            depends_on: vec![],
+        };
+
+        testing_env!(context_with_key(key.clone(), amount));
+        c.add_request_and_confirm(ft_transfer.clone());
+        assert_eq!(c.requests.len(), 0);
+        assert_eq!(c.get_remaining_allowance(Some(bob()), Some(Member::Key(key))), U128(0));
+        assert_eq!(
+            c.get_remaining_allowance(Some(bob()), Some(Member::Key(other_key.clone()))),
+            U128(100)
+        );
+        // NEAR transfers have no limit
+        assert_eq!(c.get_remaining_allowance(None, None), U128(0));
+
+        testing_env!(context_with_key(other_key, amount));
+        c.add_request_and_confirm(ft_transfer);
+        assert_eq!(c.requests.len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Receiver carol is not allowed by the policy")]
+    fn test_policy_allowed_receivers() {
+        let mut c = contract_with_request(
+            1,
+            member_request(MultiSigRequestAction::SetPolicy {
+                policy: Policy {
+                    allowed_receivers: Some(vec![bob()]),
+                    ..Policy::default()
+                },
+            }),
+        );
+        assert_eq!(c.get_policy().allowed_receivers, Some(vec![bob()]));
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: NearToken::from_yoctonear(1),
+            }],
+            depends_on: vec![],
+        });
+        c.add_request(MultiSigRequest {
+            receiver_id: AccountId::from_str("carol").unwrap(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: NearToken::from_yoctonear(1),
+            }],
+            depends_on: vec![],
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Method withdraw is not allowed by the policy")]
+    fn test_policy_allowed_methods() {
+        let mut c = contract_with_request(
+            1,
+            member_request(MultiSigRequestAction::SetPolicy {
+                policy: Policy {
+                    allowed_methods: [(bob(), vec!["ft_transfer".to_string()])].into(),
+                    ..Policy::default()
+                },
+            }),
+        );
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::FunctionCall {
+                method_name: "withdraw".to_string(),
+                args: vec![].into(),
+                deposit: NearToken::from_yoctonear(0),
+                gas: Gas::from_tgas(10),
+            }],
+            depends_on: vec![],
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Deposit exceeds the maximum allowed by the policy")]
+    fn test_policy_checked_on_execution() {
+        let mut c = contract_with_request(
+            1,
+            member_request(MultiSigRequestAction::SetPolicy {
+                policy: Policy::default(),
+            }),
+        );
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::FunctionCall {
+                method_name: "deposit".to_string(),
+                args: vec![].into(),
+                deposit: NearToken::from_near(10),
+                gas: Gas::from_tgas(10),
+            }],
+            depends_on: vec![],
+        });
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetPolicy {
+                policy: Policy {
+                    max_deposit: Some(NearToken::from_near(1)),
+                    ..Policy::default()
+                },
+            }],
+            depends_on: vec![],
+        });
+        c.confirm(request_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "SetPolicy can not be forbidden")]
+    fn test_panics_policy_forbids_set_policy() {
+        contract_with_request(
+            1,
+            member_request(MultiSigRequestAction::SetPolicy {
+                policy: Policy {
+                    forbidden_actions: vec![MultiSigRequestActionKind::SetPolicy],
+                    ..Policy::default()
+                },
+            }),
+        );
+    }
+
+    #[test]
+    fn test_unpause() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let mut c = contract_with_request(
+            1,
+            member_request(MultiSigRequestAction::SetGuardians {
+                guardians: vec![key.clone()],
+            }),
+        );
+        assert_eq!(c.list_guardians(), vec![key.clone()]);
+        c.pause();
+        assert_eq!(c.get_pause_info().unwrap().guardian, key);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::Unpause { guardians: None }],
+            depends_on: vec![],
+        });
+        assert!(c.get_pause_info().is_none());
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: NearToken::from_yoctonear(1),
+            }],
+            depends_on: vec![],
+        });
+    }
+
+    #[test]
+    fn test_unpause_replaces_guardians() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let mut c = contract_with_request(
+            1,
+            member_request(MultiSigRequestAction::SetGuardians { guardians: vec![key] }),
+        );
+        c.pause();
+        let new_guardian = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::Unpause {
+                guardians: Some(vec![new_guardian.clone()]),
+            }],
+            depends_on: vec![],
+        });
+        assert!(c.get_pause_info().is_none());
+        assert_eq!(c.list_guardians(), vec![new_guardian]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only guardian keys can pause the multisig")]
+    fn test_panics_pause_with_replaced_guardian() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let mut c = contract_with_request(
+            1,
+            member_request(MultiSigRequestAction::SetGuardians { guardians: vec![key] }),
+        );
+        c.pause();
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::Unpause {
+                guardians: Some(vec![]),
+            }],
+            depends_on: vec![],
+        });
+        c.pause();
+    }
+
+    #[test]
+    #[should_panic(expected = "Multisig is paused, only Unpause request is allowed")]
+    fn test_panics_add_request_while_paused() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let mut c = contract_with_request(
+            1,
+            member_request(MultiSigRequestAction::SetGuardians { guardians: vec![key] }),
+        );
+        c.pause();
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: NearToken::from_yoctonear(1),
+            }],
+            depends_on: vec![],
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Only guardian keys can pause the multisig")]
+    fn test_panics_pause_not_guardian() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(1);
+        c.pause();
+    }
+
+    #[test]
+    fn test_trigger_scheduled() {
+        let mut c = contract_with_request(1, schedule_request(2));
+        assert_eq!(c.list_schedule_ids(), vec![0]);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let amount = NearToken::from_yoctonear(1_000);
+
+        testing_env!(context_with_key_at(key.clone(), amount, 1_000));
+        c.trigger_scheduled(0);
+        testing_env!(context_with_key_at(key, amount, 2_500));
+        c.trigger_scheduled(0);
+        let schedule = c.get_schedule(0).unwrap();
+        assert_eq!(schedule.remaining, 0);
+        assert_eq!(
+            schedule.history,
+            vec![
+                ScheduledRun {
+                    timestamp: 1_000,
+                    succeeded: None
+                },
+                ScheduledRun {
+                    timestamp: 2_500,
+                    succeeded: None
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_completed_schedule_removed() {
+        let mut c = contract_with_request(1, schedule_request(2));
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let amount = NearToken::from_yoctonear(1_000);
+
+        testing_env!(context_with_key_at(key.clone(), amount, 1_000));
+        c.trigger_scheduled(0);
+        testing_env!(context_with_key_at(key, amount, 2_000));
+        c.trigger_scheduled(0);
+
+        set_promise_result(PromiseResult::Successful(vec![]));
+        c.on_scheduled_run(0, 1);
+        // kept until the outcome of the first run is known as well
+        assert_eq!(c.get_schedule(0).unwrap().history[1].succeeded, Some(true));
+
+        set_promise_result(PromiseResult::Failed);
+        c.on_scheduled_run(0, 0);
+        assert_eq!(c.get_schedule(0), None);
+        assert!(c.list_schedule_ids().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Next run is not due yet")]
+    fn test_panics_trigger_scheduled_not_due() {
+        let mut c = contract_with_request(1, schedule_request(2));
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key_at(
+            key.clone(),
+            NearToken::from_yoctonear(1_000),
+            1_000
+        ));
+        c.trigger_scheduled(0);
+        testing_env!(context_with_key_at(key, NearToken::from_yoctonear(1_000), 1_999));
+        c.trigger_scheduled(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "No such schedule")]
+    fn test_cancel_schedule() {
+        let mut c = contract_with_request(1, schedule_request(1));
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::CancelSchedule { schedule_id: 0 }],
+            depends_on: vec![],
+        });
+        assert!(c.get_schedule(0).is_none());
+        c.trigger_scheduled(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only Transfer and FunctionCall can be scheduled")]
+    fn test_panics_schedule_setter() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(1);
+        c.add_request(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::Schedule {
+                request: MultiSigRequest {
+                    receiver_id: alice(),
+                    actions: vec![MultiSigRequestAction::SetNumConfirmations { num_confirmations: 2 }],
+                    depends_on: vec![],
+                },
+                start: 0.into(),
+                interval: 1_000.into(),
+                count: 1,
+            }],
+            depends_on: vec![],
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Interval must be positive for more than one run")]
+    fn test_panics_schedule_zero_interval() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(1);
+        c.add_request(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::Schedule {
+                request: transfer_request(vec![]),
+                start: 0.into(),
+                interval: 0.into(),
+                count: 2,
+            }],
+            depends_on: vec![],
+        });
+    }
+
+    fn schedule_request(count: u32) -> MultiSigRequest {
+        member_request(MultiSigRequestAction::Schedule {
+            request: MultiSigRequest {
+                receiver_id: bob(),
+                actions: vec![MultiSigRequestAction::Transfer {
+                    amount: NearToken::from_yoctonear(100),
+                }],
+                depends_on: vec![],
+            },
+            start: 1_000.into(),
+            interval: 1_000.into(),
+            count,
+        })
+    }
+
+    fn context_for_receiver(timestamp: u64) -> VMContext {
+        VMContextBuilder::new()
+            .current_account_id(alice())
+            .predecessor_account_id(bob())
+            .block_timestamp(timestamp)
+            .account_balance(NearToken::from_near(1))
+            .finish()
+    }
+
+    fn stream_request() -> MultiSigRequest {
+        member_request(MultiSigRequestAction::CreateStream {
+            token_id: None,
+            receiver_id: bob(),
+            start: 1_000.into(),
+            cliff: 2_000.into(),
+            end: 11_000.into(),
+            total: 10_000.into(),
+        })
+    }
+
+    #[test]
+    fn test_claim_vested() {
+        let mut c = contract_with_request(1, stream_request());
+        testing_env!(context_for_receiver(1_999));
+        assert_eq!(c.get_claimable(0), U128(0));
+        testing_env!(context_for_receiver(2_000));
+        assert_eq!(c.get_claimable(0), U128(1_000));
+
+        testing_env!(context_for_receiver(6_000));
+        c.claim(0);
+        assert_eq!(c.get_stream(0).unwrap().claimed, U128(5_000));
+        assert_eq!(c.get_claimable(0), U128(0));
+
+        // a failed transfer can be claimed again
+        testing_env!(
+            context_for_receiver(6_000),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        c.on_claim(0, U128(5_000));
+        assert_eq!(c.get_claimable(0), U128(5_000));
+
+        testing_env!(context_for_receiver(11_000));
+        c.claim(0);
+        testing_env!(
+            context_for_receiver(11_000),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        c.on_claim(0, U128(10_000));
+        assert!(c.list_stream_ids().is_empty());
+    }
+
+    #[test]
+    fn test_stream_kept_while_claims_pending() {
+        let mut c = contract_with_request(1, stream_request());
+        testing_env!(context_for_receiver(6_000));
+        c.claim(0);
+        testing_env!(context_for_receiver(11_000));
+        c.claim(0);
+        assert_eq!(c.get_stream(0).unwrap().pending_claims, 2);
+
+        testing_env!(
+            context_for_receiver(11_000),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        c.on_claim(0, U128(5_000));
+        assert_eq!(c.list_stream_ids(), vec![0]);
+
+        // the failed claim is credited back to the stream
+        testing_env!(
+            context_for_receiver(11_000),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        c.on_claim(0, U128(5_000));
+        assert_eq!(c.get_claimable(0), U128(5_000));
+        assert_eq!(c.get_stream(0).unwrap().pending_claims, 0);
+    }
+
+    #[test]
+    fn test_revoke_stream() {
+        let mut c = contract_with_request(1, stream_request());
+        testing_env!(context_with_key_at(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000),
+            6_000
+        ));
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::RevokeStream { stream_id: 0 }],
+            depends_on: vec![],
+        });
+        // unvested tokens stay in the multisig
+        testing_env!(context_for_receiver(20_000));
+        assert_eq!(c.get_claimable(0), U128(5_000));
+        assert_eq!(c.get_stream(0).unwrap().total, U128(5_000));
+    }
+
+    #[test]
+    fn test_revoke_stream_before_cliff_removes_it() {
+        let mut c = contract_with_request(1, stream_request());
+        testing_env!(context_with_key_at(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000),
+            1_500
+        ));
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::RevokeStream { stream_id: 0 }],
+            depends_on: vec![],
+        });
+        assert_eq!(c.get_stream(0), None);
+        assert!(c.list_stream_ids().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the receiver can claim the stream")]
+    fn test_panics_claim_not_receiver() {
+        let mut c = contract_with_request(1, stream_request());
+        testing_env!(context_with_key_at(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000),
+            6_000
+        ));
+        c.claim(0);
+    }
+
+    fn transfer_request(depends_on: Vec<MultisigRequestId>) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: NearToken::from_yoctonear(1),
+            }],
+            depends_on,
+        }
+    }
+
+    fn mint_request() -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::FunctionCall {
+                method_name: "mint".to_string(),
+                args: vec![].into(),
+                deposit: NearToken::from_yoctonear(0),
+                gas: Gas::from_tgas(5),
+            }],
+            depends_on: vec![],
+        }
+    }
+
+    fn set_promise_result(result: PromiseResult) {
+        testing_env!(
+            context_with_key(
+                PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+                NearToken::from_yoctonear(1_000)
+            ),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    #[test]
+    fn test_dependent_executed_after_dependency() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(2);
+        c.add_request_and_confirm(transfer_request(vec![]));
+        c.add_request_and_confirm(transfer_request(vec![0]));
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        // blocked until request 0 is executed
+        c.confirm(1);
+        assert_eq!(c.get_confirmations(1).len(), 2);
+        c.confirm(0);
+        assert_eq!(c.list_request_ids(), vec![1]);
+
+        set_promise_result(PromiseResult::Successful(vec![]));
+        c.on_request_executed(0);
+        // released in the callback, executed separately
+        assert_eq!(c.list_request_ids(), vec![1]);
+        assert_eq!(c.list_ready_requests(), vec![1]);
+        c.execute_ready(1);
+        assert!(c.list_request_ids().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Nested request can't depend on other requests")]
+    fn test_panics_nested_request_with_dependencies() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(2);
+        c.add_request(transfer_request(vec![]));
+        c.add_request(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::Batch {
+                transactions: vec![transfer_request(vec![0])],
+            }],
+            depends_on: vec![],
+        });
+    }
+
+    #[test]
+    fn test_dependent_cancelled_on_failure() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(2);
+        c.add_request_and_confirm(transfer_request(vec![]));
+        c.add_request_and_confirm(transfer_request(vec![0]));
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        c.confirm(0);
+
+        // waits while the failed request can be retried
+        set_promise_result(PromiseResult::Failed);
+        c.on_request_executed(0);
+        assert_eq!(c.list_request_ids(), vec![1]);
+        assert_eq!(c.list_failed_request_ids(), vec![0]);
+
+        c.delete_request(0);
+        assert!(c.list_request_ids().is_empty());
+        assert!(c.list_failed_request_ids().is_empty());
+    }
+
+    #[test]
+    fn test_dependent_cancelled_on_delete() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(2);
+        c.add_request_and_confirm(transfer_request(vec![]));
+        c.add_request_and_confirm(transfer_request(vec![0]));
+        testing_env!(context_with_key_future(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        c.delete_request(0);
+        assert!(c.list_request_ids().is_empty());
+        assert_eq!(c.get_num_requests_pk(env::signer_account_pk()), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Dependency 5 is not pending")]
+    fn test_panics_dependency_not_pending() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(2);
+        c.add_request_and_confirm(transfer_request(vec![]));
+        c.add_request_and_confirm(transfer_request(vec![0]));
+        c.add_request(transfer_request(vec![5]));
+    }
+
+    #[test]
+    fn test_batch_records_transaction_results() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetActiveRequestsLimit {
+                active_requests_limit: 20,
+            }],
+            depends_on: vec![],
+        });
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::Batch {
+                transactions: vec![transfer_request(vec![]), transfer_request(vec![])],
+            }],
+            depends_on: vec![],
+        });
+
+        testing_env!(
+            context_with_key(
+                PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+                NearToken::from_yoctonear(1_000)
+            ),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![]), PromiseResult::Failed],
+        );
+        c.on_batch_executed(request_id);
+        let history = c.list_history(0, 10);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].outcome, RequestOutcome::Succeeded);
+        assert_eq!(history[1].request_id, request_id);
+        assert_eq!(
+            history[1].outcome,
+            RequestOutcome::Batch {
+                results: vec![true, false]
+            }
+        );
+        assert_eq!(c.list_history(1, 10).len(), 1);
+    }
+
+    #[test]
+    fn test_retry_failed_batch() {
+        let amount = NearToken::from_yoctonear(1_000);
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), amount));
+        let mut c = MultiSigContract::new(1);
+        let carol_transfer = MultiSigRequest {
+            receiver_id: "carol.near".parse().unwrap(),
+            ..transfer_request(vec![])
+        };
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::Batch {
+                transactions: vec![transfer_request(vec![]), carol_transfer],
+            }],
+            depends_on: vec![],
+        });
+        testing_env!(
+            context_with_key(key.clone(), amount),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![]), PromiseResult::Failed],
+        );
+        c.on_batch_executed(request_id);
+        let failed = c.get_failed_request(request_id).unwrap();
+        assert_eq!(failed.attempts, 1);
+        assert_eq!(failed.batch_results, Some(vec![true, false]));
+
+        testing_env!(context_with_key(key.clone(), amount));
+        c.retry(request_id, None);
+        assert_eq!(c.get_failed_request(request_id), None);
+        // the transfer to bob succeeded before and isn't sent again
+        let receivers: Vec<String> = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .map(|receipt| receipt.receiver_id.to_string())
+            .collect();
+        assert!(receivers.contains(&"carol.near".to_string()));
+        assert!(!receivers.contains(&bob().to_string()));
+
+        testing_env!(
+            context_with_key(key, amount),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        c.on_batch_executed(request_id);
+        assert_eq!(c.get_failed_request(request_id), None);
+        assert!(c.executing.get(&request_id).is_none());
+        assert_eq!(
+            c.list_history(0, 10).last().unwrap().outcome,
+            RequestOutcome::Batch {
+                results: vec![true, true]
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Only Transfer and FunctionCall can be batched")]
+    fn test_panics_batch_with_setter() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        let mut c = MultiSigContract::new(1);
+        c.add_request(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::Batch {
+                transactions: vec![MultiSigRequest {
+                    receiver_id: alice(),
+                    actions: vec![MultiSigRequestAction::SetNumConfirmations { num_confirmations: 2 }],
+                    depends_on: vec![],
+                }],
+            }],
+            depends_on: vec![],
+        });
+    }
+
+    #[test]
+    fn test_retry_failed_request() {
+        let mut c = contract_with_request(1, mint_request());
+        set_promise_result(PromiseResult::Failed);
+        c.on_request_executed(0);
+        let failed = c.get_failed_request(0).unwrap();
+        assert_eq!(failed.attempts, 1);
+        assert_eq!(
+            failed.confirmations,
+            vec![Member::Key(
+                PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap()
+            )]
+        );
+
+        c.retry(0, Some(Gas::from_tgas(50)));
+        assert!(c.get_failed_request(0).is_none());
+        set_promise_result(PromiseResult::Failed);
+        c.on_request_executed(0);
+        let failed = c.get_failed_request(0).unwrap();
+        assert_eq!(failed.attempts, 2);
+        assert!(matches!(
+            failed.request.actions[0],
+            MultiSigRequestAction::FunctionCall { gas, .. } if gas == Gas::from_tgas(50)
+        ));
+
+        c.retry(0, None);
+        set_promise_result(PromiseResult::Successful(vec![]));
+        c.on_request_executed(0);
+        assert!(c.list_failed_request_ids().is_empty());
+        assert_eq!(c.list_history(0, 10).len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough confirmations left to retry")]
+    fn test_panics_retry_after_confirming_key_removed() {
+        let mut c = contract_with_request(1, mint_request());
+        set_promise_result(PromiseResult::Failed);
+        c.on_request_executed(0);
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::DeleteKey {
+            public_key: PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+        }));
+        assert!(c.get_failed_request(0).unwrap().confirmations.is_empty());
+        c.retry(0, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Retry window is over")]
+    fn test_panics_retry_after_window() {
+        let mut c = contract_with_request(1, mint_request());
+        set_promise_result(PromiseResult::Failed);
+        c.on_request_executed(0);
+        testing_env!(context_with_key_at(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_yoctonear(1_000),
+            RetryConfig::default().window.0 + 1
+        ));
+        c.retry(0, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Request cannot be deleted immediately after creation.")]
+    fn test_panics_delete_failed_request_of_other_member() {
+        let mut c = contract_with_request(1, mint_request());
+        set_promise_result(PromiseResult::Failed);
+        c.on_request_executed(0);
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        c.delete_request(0);
+    }
+
+    #[test]
+    fn test_delete_failed_request_quorum() {
+        let mut c = contract_with_request(1, mint_request());
+        set_promise_result(PromiseResult::Failed);
+        c.on_request_executed(0);
+        let other_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        testing_env!(context_with_key_future(
+            other_key.clone(),
+            NearToken::from_yoctonear(1_000)
+        ));
+        assert!(matches!(c.delete_request(0), DeleteOutcome::Deleted { .. }));
+        assert!(c.list_failed_request_ids().is_empty());
+        assert!(c.delete_voted_requests.get(&Member::Key(other_key.clone())).is_none());
+        assert_eq!(
+            c.list_history(0, 10)[1].outcome,
+            RequestOutcome::Deleted {
+                deleted_by: vec![Member::Key(other_key)]
+            }
+        );
+    }
+
+    #[test]
+    fn test_execution_result() {
+        let mut c = contract_with_request(1, mint_request());
+        set_promise_result(PromiseResult::Failed);
+        c.on_request_executed(0);
+        let result = c.get_execution_result(0).unwrap();
+        assert!(!result.success);
+        assert_eq!(result.json, None);
+
+        c.retry(0, None);
+        set_promise_result(PromiseResult::Successful(br#"{"proposal_id": 7}"#.to_vec()));
+        c.on_request_executed(0);
+        let result = c.get_execution_result(0).unwrap();
+        assert!(result.success);
+        assert!(!result.truncated);
+        assert_eq!(result.json.unwrap()["proposal_id"], 7);
+        assert_eq!(c.get_execution_result(1), None);
+    }
+
+    #[test]
+    fn test_execution_result_truncated() {
+        let mut c = contract_with_request(1, mint_request());
+        set_promise_result(PromiseResult::Failed);
+        c.on_request_executed(0);
+        c.retry(0, None);
+        set_promise_result(PromiseResult::Successful(vec![b'1'; 2_000]));
+        c.on_request_executed(0);
+        let result = c.get_execution_result(0).unwrap();
+        assert!(result.success);
+        assert!(result.truncated);
+        assert!(result.value.0.is_empty());
+        assert_eq!(result.json, None);
+    }
+
+    fn context_for_member(account_id: AccountId) -> VMContext {
+        VMContextBuilder::new()
+            .current_account_id(alice())
+            .predecessor_account_id(account_id.clone())
+            .signer_account_id(account_id)
+            .account_balance(NearToken::from_near(1))
+            .finish()
+    }
+
+    fn member_request(action: MultiSigRequestAction) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![action],
+            depends_on: vec![],
+        }
+    }
+
+    #[test]
+    fn test_member_account_confirms() {
+        let mut c = contract_with_request(
+            2,
+            member_request(MultiSigRequestAction::AddMember { account_id: bob() }),
+        );
+        let request_id = c.add_request_and_confirm(transfer_request(vec![]));
+        assert_eq!(c.get_confirmations(request_id).len(), 1);
+
+        testing_env!(context_for_member(bob()));
+        c.confirm(request_id);
+        assert!(c.list_request_ids().is_empty());
+
+        let request_id = c.add_request_and_confirm(transfer_request(vec![]));
+        assert_eq!(c.list_requests_by_signer(Member::Account(bob())), vec![request_id]);
+        assert_eq!(c.get_confirmations(request_id), vec![Member::Account(bob())]);
+    }
+
+    #[test]
+    fn test_remove_member() {
+        let mut c = contract_with_request(
+            2,
+            member_request(MultiSigRequestAction::AddMember { account_id: bob() }),
+        );
+        let request_id = c.add_request(transfer_request(vec![]));
+        testing_env!(context_for_member(bob()));
+        c.confirm(request_id);
+        let own_request_id = c.add_request(transfer_request(vec![]));
+
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_near(1)
+        ));
+        let remove_id = c.add_request_and_confirm(member_request(MultiSigRequestAction::RemoveMember {
+            account_id: bob(),
+        }));
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            NearToken::from_near(1)
+        ));
+        c.confirm(remove_id);
+
+        assert!(c.list_members().is_empty());
+        assert_eq!(c.list_request_ids(), vec![request_id]);
+        assert!(c.get_confirmations(request_id).is_empty());
+        assert!(c.list_requests_by_signer(Member::Account(bob())).is_empty());
+        assert!(!c.requests.contains_key(&own_request_id));
+    }
+
+    #[test]
+    #[should_panic(expected = "Predecessor account must much current account")]
+    fn test_panics_add_request_not_member() {
+        let mut c = contract_with_request(
+            2,
+            member_request(MultiSigRequestAction::AddMember { account_id: bob() }),
+        );
+        testing_env!(context_for_member(carol()));
+        c.add_request(transfer_request(vec![]));
+    }
+
+    #[test]
+    fn test_new_with_config() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), NearToken::from_near(1)));
+        let c = MultiSigContract::new_with_config(
+            vec![Member::Key(key), Member::Account(bob())],
+            2,
+            None,
+            Some(5),
+            None,
+            Some(U64(REQUEST_COOLDOWN * 4)),
+        );
+        assert_eq!(
+            c.get_config(),
+            MultisigConfig {
+                num_confirmations: 2,
+                elevated_num_confirmations: 2,
+                active_requests_limit: 5,
+                request_cooldown: U64(REQUEST_COOLDOWN),
+                cooldown_overrides: BTreeMap::new(),
+                request_expiry: Some(U64(REQUEST_COOLDOWN * 4)),
+                retry_config: RetryConfig::default(),
+                confirmations_policy: ConfirmationsPolicy::DropRemoved,
+            }
+        );
+        assert_eq!(c.list_members(), vec![bob()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Number of confirmations must be between 1 and the number of members")]
+    fn test_panics_config_threshold_above_members() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), NearToken::from_near(1)));
+        MultiSigContract::new_with_config(vec![Member::Key(key)], 2, None, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Request has expired")]
+    fn test_panics_confirm_expired_request() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), NearToken::from_near(1)));
+        let other_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        let mut c = MultiSigContract::new_with_config(
+            vec![Member::Key(key), Member::Key(other_key.clone())],
+            2,
+            None,
+            None,
+            None,
+            Some(U64(REQUEST_COOLDOWN * 4)),
+        );
+        let request_id = c.add_request_and_confirm(transfer_request(vec![]));
+        testing_env!(context_with_key_at(
+            other_key,
+            NearToken::from_near(1),
+            REQUEST_COOLDOWN * 4 + 1
+        ));
+        c.confirm(request_id);
+    }
+
+    #[test]
+    fn test_request_cooldown_override() {
+        let mut c = contract_with_request(
+            1,
+            member_request(MultiSigRequestAction::SetRequestCooldown {
+                cooldown: U64(REQUEST_COOLDOWN),
+                overrides: BTreeMap::from([(MultiSigRequestActionKind::Transfer, U64(REQUEST_COOLDOWN * 2))]),
+            }),
+        );
+        c.add_request(transfer_request(vec![]));
+        assert_eq!(c.get_request_cooldown(None), U64(REQUEST_COOLDOWN));
+        assert_eq!(
+            c.get_request_cooldown(Some(MultiSigRequestActionKind::Transfer)),
+            U64(REQUEST_COOLDOWN * 2)
+        );
+        testing_env!(context_with_key_at(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            NearToken::from_near(1),
+            REQUEST_COOLDOWN * 2 + 1
+        ));
+        c.delete_request(1);
+        assert!(c.list_request_ids().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Request cannot be deleted immediately after creation.")]
+    fn test_panics_delete_before_cooldown_override() {
+        let mut c = contract_with_request(
+            1,
+            member_request(MultiSigRequestAction::SetRequestCooldown {
+                cooldown: U64(REQUEST_COOLDOWN),
+                overrides: BTreeMap::from([(MultiSigRequestActionKind::Transfer, U64(REQUEST_COOLDOWN * 2))]),
+            }),
+        );
+        c.add_request(transfer_request(vec![]));
+        testing_env!(context_with_key_future(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            NearToken::from_near(1)
+        ));
+        c.delete_request(1);
+    }
+
+    #[test]
+    fn test_update_config() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_near(1)
+        ));
+        let mut c = MultiSigContract::new(1);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::UpdateConfig {
+            update: Box::new(ConfigUpdate {
+                num_confirmations: Some(2),
+                active_requests_limit: Some(3),
+                cooldown_overrides: Some(BTreeMap::from([(
+                    MultiSigRequestActionKind::Transfer,
+                    U64(REQUEST_COOLDOWN * 2),
+                )])),
+                request_expiry: Some(U64(REQUEST_COOLDOWN * 4)),
+                ..ConfigUpdate::default()
+            }),
+        }));
+        assert_eq!(
+            c.get_config(),
+            MultisigConfig {
+                num_confirmations: 2,
+                elevated_num_confirmations: 2,
+                active_requests_limit: 3,
+                request_cooldown: U64(REQUEST_COOLDOWN),
+                cooldown_overrides: BTreeMap::from([(MultiSigRequestActionKind::Transfer, U64(REQUEST_COOLDOWN * 2))]),
+                request_expiry: Some(U64(REQUEST_COOLDOWN * 4)),
+                retry_config: RetryConfig::default(),
+                confirmations_policy: ConfirmationsPolicy::DropRemoved,
+            }
+        );
+    }
+
+    #[test]
+    fn test_update_config_spending_limits_guardians_and_recovery() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), NearToken::from_near(1)));
+        let mut c = MultiSigContract::new(1);
+        let limit = SpendingLimit {
+            amount: 1_000.into(),
+            period: 86_400_000_000_000.into(),
+            per_member: false,
+        };
+        let recovery = RecoveryConfig {
+            accounts: vec![carol()],
+            delay: 86_400_000_000_000.into(),
+            inactivity_period: 86_400_000_000_000.into(),
+        };
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::UpdateConfig {
+            update: Box::new(ConfigUpdate {
+                spending_limits: Some(vec![TokenSpendingLimit {
+                    token_id: None,
+                    limit: Some(limit.clone()),
+                }]),
+                guardians: Some(vec![key.clone()]),
+                recovery: Some(Some(recovery.clone())),
+                ..ConfigUpdate::default()
+            }),
+        }));
+        assert_eq!(c.get_spending_limit(None), Some(limit));
+        assert_eq!(c.list_guardians(), vec![key]);
+        assert_eq!(c.get_recovery_config(), Some(recovery));
+
+        // a missing field keeps the setting, null disables recovery
+        let update: ConfigUpdate =
+            near_sdk::serde_json::from_str(r#"{"spending_limits": [{"token_id": null, "limit": null}]}"#).unwrap();
+        assert_eq!(update.recovery, None);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::UpdateConfig {
+            update: Box::new(update),
+        }));
+        assert_eq!(c.get_spending_limit(None), None);
+        assert!(c.get_recovery_config().is_some());
+
+        let update: ConfigUpdate = near_sdk::serde_json::from_str(r#"{"recovery": null}"#).unwrap();
+        assert_eq!(update.recovery, Some(None));
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::UpdateConfig {
+            update: Box::new(update),
+        }));
+        assert_eq!(c.get_recovery_config(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Recovery delay must be at least")]
+    fn test_panics_update_config_invalid_recovery() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_near(1)
+        ));
+        let mut c = MultiSigContract::new(1);
+        c.add_request(member_request(MultiSigRequestAction::UpdateConfig {
+            update: Box::new(ConfigUpdate {
+                recovery: Some(Some(RecoveryConfig {
+                    accounts: vec![carol()],
+                    delay: 0.into(),
+                    inactivity_period: 0.into(),
+                })),
+                ..ConfigUpdate::default()
+            }),
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Number of confirmations must be between 1 and the number of members")]
+    fn test_panics_set_num_confirmations_above_members() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key.clone(), NearToken::from_near(1)));
+        let mut c = MultiSigContract::new_with_config(vec![Member::Key(key)], 1, None, None, None, None);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::SetNumConfirmations {
+            num_confirmations: 2,
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Request expiry must be longer than the request cooldown")]
+    fn test_panics_update_config_expiry_below_override() {
+        let mut c = contract_with_request(
+            1,
+            member_request(MultiSigRequestAction::SetRequestCooldown {
+                cooldown: U64(REQUEST_COOLDOWN),
+                overrides: BTreeMap::from([(MultiSigRequestActionKind::Transfer, U64(REQUEST_COOLDOWN * 2))]),
+            }),
+        );
+        c.add_request(transfer_request(vec![]));
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::UpdateConfig {
+            update: Box::new(ConfigUpdate {
+                num_confirmations: Some(2),
+                request_expiry: Some(U64(REQUEST_COOLDOWN + 1)),
+                ..ConfigUpdate::default()
+            }),
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "SetNumConfirmations is forbidden by the policy")]
+    fn test_panics_update_config_forbidden_setting() {
+        let mut c = contract_with_request(
+            1,
+            member_request(MultiSigRequestAction::SetPolicy {
+                policy: Policy {
+                    forbidden_actions: vec![MultiSigRequestActionKind::SetNumConfirmations],
+                    ..Policy::default()
+                },
+            }),
+        );
+        c.add_request(member_request(MultiSigRequestAction::UpdateConfig {
+            update: Box::new(ConfigUpdate {
+                num_confirmations: Some(2),
+                ..ConfigUpdate::default()
+            }),
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "SetConfirmationsPolicy is forbidden by the policy")]
+    fn test_panics_update_config_forbidden_confirmations_policy() {
+        let mut c = contract_with_request(
+            1,
+            member_request(MultiSigRequestAction::SetPolicy {
+                policy: Policy {
+                    forbidden_actions: vec![MultiSigRequestActionKind::SetConfirmationsPolicy],
+                    ..Policy::default()
+                },
+            }),
+        );
+        c.add_request(member_request(MultiSigRequestAction::UpdateConfig {
+            update: Box::new(ConfigUpdate {
+                confirmations_policy: Some(ConfirmationsPolicy::Keep),
+                ..ConfigUpdate::default()
+            }),
+        }));
+    }
+
+    #[test]
+    fn test_execute_ready_after_threshold_lowered() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_near(1)
+        ));
+        let mut c = MultiSigContract::new(2);
+        let request_id = c.add_request_and_confirm(transfer_request(vec![]));
+        let lower_id = c.add_request_and_confirm(member_request(MultiSigRequestAction::SetNumConfirmations {
+            num_confirmations: 1,
+        }));
+        assert!(c.list_ready_requests().is_empty());
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            NearToken::from_near(1)
+        ));
+        c.confirm(lower_id);
+        assert_eq!(c.list_ready_requests(), vec![request_id]);
+        c.execute_ready(request_id);
+        assert!(c.list_request_ids().is_empty());
+    }
+
+    #[test]
+    fn test_confirm_executes_ready_request() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_near(1)
+        ));
+        let mut c = MultiSigContract::new(2);
+        let request_id = c.add_request_and_confirm(transfer_request(vec![]));
+        let lower_id = c.add_request_and_confirm(member_request(MultiSigRequestAction::SetNumConfirmations {
+            num_confirmations: 1,
+        }));
+        testing_env!(context_with_key(
+            PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            NearToken::from_near(1)
+        ));
+        c.confirm(lower_id);
+        // a key that confirmed the request already executes it by confirming again
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_near(1)
+        ));
+        c.confirm(request_id);
+        assert!(c.list_request_ids().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Request is not ready to be executed")]
+    fn test_panics_execute_ready_not_confirmed() {
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_near(1)
+        ));
+        let mut c = MultiSigContract::new(2);
+        let request_id = c.add_request_and_confirm(transfer_request(vec![]));
+        c.execute_ready(request_id);
+    }
+
+    #[test]
+    fn test_reset_confirmations_on_threshold_raise() {
+        let mut c = contract_with_request(
+            2,
+            member_request(MultiSigRequestAction::UpdateConfig {
+                update: Box::new(ConfigUpdate {
+                    confirmations_policy: Some(ConfirmationsPolicy::ResetAll),
+                    ..ConfigUpdate::default()
+                }),
+            }),
+        );
+        let request_id = c.add_request_and_confirm(transfer_request(vec![]));
+        let raise_id = c.add_request_and_confirm(member_request(MultiSigRequestAction::SetNumConfirmations {
+            num_confirmations: 3,
+        }));
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_near(1)
+        ));
+        c.confirm(raise_id);
+        assert_eq!(c.get_num_confirmations(), 3);
+        assert!(c.get_confirmations(request_id).is_empty());
+        let signer = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        assert!(!c.confirmed_requests.contains_key(&Member::Key(signer)));
+    }
+
+    #[test]
+    fn test_reset_all_keeps_confirmations_on_non_signer_key() {
+        let mut c = contract_with_request(
+            2,
+            member_request(MultiSigRequestAction::UpdateConfig {
+                update: Box::new(ConfigUpdate {
+                    confirmations_policy: Some(ConfirmationsPolicy::ResetAll),
+                    ..ConfigUpdate::default()
+                }),
+            }),
+        );
+        let signer = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        let request_id = c.add_request_and_confirm(transfer_request(vec![]));
+        // the key can't call the multisig, so the members don't change
+        let add_key_id = c.add_request_and_confirm(member_request(MultiSigRequestAction::AddKey {
+            public_key: PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap(),
+            permission: Some(FunctionCallPermission {
+                allowance: None,
+                receiver_id: bob(),
+                method_names: vec![],
+            }),
+        }));
+        testing_env!(context_with_key(
+            PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap(),
+            NearToken::from_near(1)
+        ));
+        c.confirm(add_key_id);
+        assert_eq!(c.list_request_ids(), vec![request_id]);
+        assert_eq!(c.get_confirmations(request_id), vec![Member::Key(signer)]);
+    }
+
+    #[test]
+    fn test_keep_confirmations_of_deleted_key() {
+        let mut c = contract_with_request(
+            2,
+            member_request(MultiSigRequestAction::UpdateConfig {
+                update: Box::new(ConfigUpdate {
+                    confirmations_policy: Some(ConfirmationsPolicy::Keep),
+                    ..ConfigUpdate::default()
+                }),
+            }),
+        );
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let request_id = c.add_request(transfer_request(vec![]));
+        testing_env!(context_with_key(key.clone(), NearToken::from_near(1)));
+        c.confirm(request_id);
+
+        let delete_id = c.add_request_and_confirm(member_request(MultiSigRequestAction::DeleteKey {
+            public_key: key.clone(),
+        }));
+        testing_env!(context_with_key(
+            PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap(),
+            NearToken::from_near(1)
+        ));
+        c.confirm(delete_id);
+        assert_eq!(c.get_confirmations(request_id), vec![Member::Key(key)]);
+    }
+
+    #[test]
+    fn test_rotate_key() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let new_key = PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap();
+        let other_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        testing_env!(context_with_key(key.clone(), NearToken::from_near(1)));
+        let mut c = MultiSigContract::new(2);
+        // the keys were added outside of the multisig, their permission must be registered to rotate them
+        let register_id = c.add_request_and_confirm(member_request(MultiSigRequestAction::RegisterSignerKeys {
+            keys: vec![SignerKey {
+                public_key: key.clone(),
+                permission: signer_permission(None),
+            }],
+        }));
+        let request_id = c.add_request_and_confirm(transfer_request(vec![]));
+        let rotate_id = c.add_request_and_confirm(member_request(MultiSigRequestAction::RotateKey {
+            old: key.clone(),
+            new: new_key.clone(),
+        }));
+        testing_env!(context_with_key(other_key, NearToken::from_near(1)));
+        c.confirm(register_id);
+        c.confirm(rotate_id);
+
+        assert_eq!(c.list_request_ids(), vec![request_id]);
+        assert_eq!(c.get_num_requests_pk(key.clone()), 0);
+        assert_eq!(c.get_num_requests_pk(new_key.clone()), 1);
+        assert_eq!(
+            c.requests.get(&request_id).unwrap().signer,
+            Member::Key(new_key.clone())
+        );
+        assert_eq!(c.get_confirmations(request_id), vec![Member::Key(new_key)]);
+        assert!(c.revoked_keys.contains(&key));
+    }
+
+    #[test]
+    #[should_panic(expected = "New key is already a signer key")]
+    fn test_panics_rotate_to_signer_key() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let other_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        let signer_key = PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap();
+        testing_env!(context_with_key(other_key.clone(), NearToken::from_near(1)));
+        let mut c = MultiSigContract::new(1);
+        c.add_request(transfer_request(vec![]));
+        testing_env!(context_with_key(key, NearToken::from_near(1)));
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::AddSignerKey {
+            public_key: signer_key.clone(),
+            allowance: None,
+        }));
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::RotateKey {
+            old: signer_key,
+            new: other_key,
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "New key is already a signer key")]
+    fn test_panics_rotate_to_signer_key_without_requests() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let signer_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        let other_key = PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap();
+        testing_env!(context_with_key(key, NearToken::from_near(1)));
+        let mut c = MultiSigContract::new(1);
+        for public_key in [signer_key.clone(), other_key.clone()] {
+            c.add_request_and_confirm(member_request(MultiSigRequestAction::AddSignerKey {
+                public_key,
+                allowance: None,
+            }));
+        }
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::RotateKey {
+            old: signer_key,
+            new: other_key,
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "New key was deleted from the multisig")]
+    fn test_panics_rotate_to_deleted_key() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let signer_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        let deleted_key = PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap();
+        testing_env!(context_with_key(key, NearToken::from_near(1)));
+        let mut c = MultiSigContract::new(1);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::AddSignerKey {
+            public_key: signer_key.clone(),
+            allowance: None,
+        }));
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::DeleteKey {
+            public_key: deleted_key.clone(),
+        }));
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::RotateKey {
+            old: signer_key,
+            new: deleted_key,
+        }));
+    }
+
+    #[test]
+    fn test_rotate_key_keeps_allowance() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let signer_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        let new_key = PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap();
+        let allowance = U128(NearToken::from_near(1).as_yoctonear());
+        testing_env!(context_with_key(key.clone(), NearToken::from_near(1)));
+        let mut c = MultiSigContract::new(1);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::AddSignerKey {
+            public_key: signer_key.clone(),
+            allowance: Some(allowance),
+        }));
+        let rotate_id = c.add_request_and_confirm(member_request(MultiSigRequestAction::RotateKey {
+            old: signer_key.clone(),
+            new: new_key.clone(),
+        }));
+        assert!(c.list_request_ids().is_empty(), "request {rotate_id} is executed");
+        assert_eq!(
+            c.key_permissions.get(&new_key),
+            Some(&signer_permission(Some(allowance)))
+        );
+        assert_eq!(c.key_permissions.get(&signer_key), None);
+        assert_eq!(c.list_signer_keys(), vec![key, new_key]);
+    }
+
+    #[test]
+    fn test_rotate_key_keeps_permission_and_guardian() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let signer_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        let new_key = PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap();
+        let permission = FunctionCallPermission {
+            allowance: None,
+            receiver_id: alice(),
+            method_names: vec!["confirm".to_string()],
+        };
+        testing_env!(context_with_key(key.clone(), NearToken::from_near(1)));
+        let mut c = MultiSigContract::new(1);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::AddKey {
+            public_key: signer_key.clone(),
+            permission: Some(permission.clone()),
+        }));
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::SetGuardians {
+            guardians: vec![signer_key.clone()],
+        }));
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::RotateKey {
+            old: signer_key,
+            new: new_key.clone(),
+        }));
+        assert_eq!(c.key_permissions.get(&new_key), Some(&permission));
+        assert_eq!(c.list_guardians(), vec![new_key]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Permission of the key is unknown, register it with RegisterSignerKeys first")]
+    fn test_panics_rotate_unknown_key() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key, NearToken::from_near(1)));
+        let mut c = MultiSigContract::new(1);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::RotateKey {
+            old: PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap(),
+            new: PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap(),
+        }));
+    }
+
+    #[test]
+    fn test_full_access_key_requires_all_members() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let signer_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        testing_env!(context_with_key(key.clone(), NearToken::from_near(1)));
+        let mut c = MultiSigContract::new_with_config(vec![Member::Key(key.clone())], 1, None, None, None, None);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::AddSignerKey {
+            public_key: signer_key.clone(),
+            allowance: Some(U128(NearToken::from_near(1).as_yoctonear())),
+        }));
+        assert_eq!(c.list_signer_keys(), vec![key, signer_key.clone()]);
+
+        let request_id = c.add_request_and_confirm(member_request(MultiSigRequestAction::AddKey {
+            public_key: PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap(),
+            permission: None,
+        }));
+        assert_eq!(c.list_request_ids(), vec![request_id]);
+        testing_env!(context_with_key(signer_key, NearToken::from_near(1)));
+        c.confirm(request_id);
+        assert!(c.list_request_ids().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "All signer keys must be known to add a full access key")]
+    fn test_panics_full_access_key_with_unseen_keys() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        testing_env!(context_with_key(key, NearToken::from_near(1)));
+        // the other keys were added with the contract and never added or confirmed a request
+        let mut c = MultiSigContract::new(2);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::AddKey {
+            public_key: PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap(),
+            permission: None,
+        }));
+    }
+
+    #[test]
+    fn test_full_access_key_after_registering_keys() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let other_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        testing_env!(context_with_key(key.clone(), NearToken::from_near(1)));
+        let mut c = MultiSigContract::new(1);
+        assert!(!c.are_signer_keys_complete());
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::RegisterSignerKeys {
+            keys: vec![SignerKey {
+                public_key: other_key.clone(),
+                permission: signer_permission(None),
+            }],
+        }));
+        assert!(c.are_signer_keys_complete());
+        assert_eq!(c.list_signer_keys(), vec![key, other_key.clone()]);
+
+        let request_id = c.add_request_and_confirm(member_request(MultiSigRequestAction::AddKey {
+            public_key: PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap(),
+            permission: None,
+        }));
+        assert_eq!(c.list_request_ids(), vec![request_id]);
+        testing_env!(context_with_key(other_key, NearToken::from_near(1)));
+        c.confirm(request_id);
+        assert!(c.list_request_ids().is_empty());
+    }
+
+    #[test]
+    fn test_delete_key_forgets_signer_key() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let signer_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        testing_env!(context_with_key(key.clone(), NearToken::from_near(1)));
+        let mut c = MultiSigContract::new(1);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::AddSignerKey {
+            public_key: signer_key.clone(),
+            allowance: None,
+        }));
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::DeleteKey {
+            public_key: signer_key,
+        }));
+        assert_eq!(c.list_signer_keys(), vec![key]);
+    }
+
+    #[test]
+    fn test_function_call_key_to_self_is_signer_key() {
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let signer_key = PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap();
+        testing_env!(context_with_key(key.clone(), NearToken::from_near(1)));
+        let mut c = MultiSigContract::new_with_config(vec![Member::Key(key.clone())], 1, None, None, None, None);
+        c.add_request_and_confirm(member_request(MultiSigRequestAction::AddKey {
+            public_key: signer_key.clone(),
+            permission: Some(FunctionCallPermission {
+                allowance: None,
+                receiver_id: alice(),
+                method_names: vec!["confirm".to_string()],
+            }),
+        }));
+        assert_eq!(c.list_signer_keys(), vec![key, signer_key]);
+
+        let request_id = c.add_request_and_confirm(member_request(MultiSigRequestAction::AddKey {
+            public_key: PublicKey::from_str("2EfbwnQHPBWQKbNczLiVznFghh9qs716QT71zN6L1D95").unwrap(),
+            permission: None,
+        }));
+        assert_eq!(c.list_request_ids(), vec![request_id]);
+    }
 }
@@ -0,0 +1,208 @@
+use multisig_model::{
+    FunctionCallPermission, Member, MultiSigRequest, MultisigRequestId, MultisigView, SIGNER_METHODS,
+};
+use near_sdk::{env, json_types::U128, AccountId, Allowance, NearToken, Promise, PublicKey};
+
+use crate::{insert_member_request, is_unanimous, remove_member_request, MultiSigContract, StorageKey};
+
+impl MultiSigContract {
+    // The member calling the multisig: the signing key if called through an access key of the multisig,
+    // otherwise the member account calling it directly - used by signer methods
+    pub(crate) fn assert_member(&self) -> Member {
+        let predecessor = env::predecessor_account_id();
+        if predecessor == env::current_account_id() {
+            return Member::Key(env::signer_account_pk());
+        }
+        assert!(
+            self.members.contains(&predecessor),
+            "Predecessor account must much current account or be a member account"
+        );
+        Member::Account(predecessor)
+    }
+
+    // Keys become known signers when they add or confirm a request - used in add_request and confirm
+    pub(crate) fn register_signer_key(&mut self, member: &Member) {
+        if let Member::Key(public_key) = member {
+            self.signer_keys.insert(public_key.clone());
+        }
+    }
+
+    // Recovery can't remove keys that acted recently - used when a member acts or a signer key is added
+    pub(crate) fn record_activity(&mut self, member: &Member) {
+        self.last_activity.insert(member.clone(), env::block_timestamp());
+    }
+
+    // All known members: signer keys and member accounts - used for unanimous requests
+    pub(crate) fn num_members(&self) -> usize {
+        (self.signer_keys.len() + self.members.len()) as usize
+    }
+
+    // Unanimous requests can't count all members while keys added outside of the multisig aren't registered
+    // - used in add_request
+    pub(crate) fn assert_signer_keys_known(&self, request: &MultiSigRequest) {
+        if is_unanimous(request) {
+            self.assert_signer_keys_complete();
+        }
+    }
+
+    // used in add_request and execute_request
+    pub(crate) fn assert_signer_keys_complete(&self) {
+        assert!(
+            self.signer_keys_complete,
+            "All signer keys must be known to add a full access key, register them with RegisterSignerKeys first"
+        );
+    }
+
+    // used in execute_request
+    pub(crate) fn add_member(&mut self, account_id: AccountId) {
+        assert_ne!(
+            account_id,
+            env::current_account_id(),
+            "Multisig can't be its own member account"
+        );
+        self.members.insert(account_id);
+        self.invalidate_confirmations(None);
+    }
+
+    // Same as DeleteKey for a key: its pending requests and confirmations are removed - used in execute_request
+    pub(crate) fn remove_member(&mut self, account_id: AccountId) {
+        assert!(self.members.remove(&account_id), "No such member account");
+        self.remove_signer(&Member::Account(account_id));
+    }
+
+    // Keeps the permission of a signer key to add its replacement with the same one, None for full access keys
+    // - used in execute_request
+    pub(crate) fn set_key_permission(&mut self, public_key: &PublicKey, permission: Option<FunctionCallPermission>) {
+        // a new key hasn't had a chance to act yet
+        self.record_activity(&Member::Key(public_key.clone()));
+        match permission {
+            Some(permission) => {
+                self.key_permissions.insert(public_key.clone(), permission);
+            }
+            None => {
+                self.key_permissions.remove(public_key);
+            }
+        }
+    }
+
+    // Moves the pending requests, confirmations, delete votes, guardian rights and the permission of the old key
+    // to the new one, returns the permission for the new key - used in execute_request
+    pub(crate) fn rotate_key(&mut self, old: &PublicKey, new: &PublicKey) -> FunctionCallPermission {
+        // keys added outside of the multisig might have any permission
+        let permission = self
+            .key_permissions
+            .get(old)
+            .cloned()
+            .expect("Permission of the key is unknown, register it with RegisterSignerKeys first");
+        assert_ne!(old, new, "New key must differ from the old key");
+        // rotating onto another signer would merge two members into one
+        assert!(!self.signer_keys.contains(new), "New key is already a signer key");
+        // a deleted key must be added back explicitly with AddKey or AddSignerKey
+        assert!(
+            !self.revoked_keys.contains(new),
+            "New key was deleted from the multisig"
+        );
+        let old_member = Member::Key(old.clone());
+        let new_member = Member::Key(new.clone());
+        assert!(
+            !self.requests_by_signer.contains_key(&new_member) && !self.confirmed_requests.contains_key(&new_member),
+            "New key already has pending requests or confirmations"
+        );
+
+        for request_id in self.list_requests_by_signer(old_member.clone()) {
+            remove_member_request(&mut self.requests_by_signer, &old_member, request_id);
+            insert_member_request(
+                &mut self.requests_by_signer,
+                new_member.clone(),
+                request_id,
+                StorageKey::SignerRequests {
+                    member: new_member.clone(),
+                },
+            );
+            self.requests.get_mut(&request_id).unwrap().signer = new_member.clone();
+        }
+
+        let confirmed_ids: Vec<MultisigRequestId> = self
+            .confirmed_requests
+            .get(&old_member)
+            .map(|request_ids| request_ids.iter().copied().collect())
+            .unwrap_or_default();
+        for request_id in confirmed_ids {
+            remove_member_request(&mut self.confirmed_requests, &old_member, request_id);
+            insert_member_request(
+                &mut self.confirmed_requests,
+                new_member.clone(),
+                request_id,
+                StorageKey::MemberConfirmedRequests {
+                    member: new_member.clone(),
+                },
+            );
+            let confirmations = self.confirmations.get_mut(&request_id).unwrap();
+            confirmations.remove(&old_member);
+            confirmations.insert(new_member.clone());
+        }
+
+        let voted_ids: Vec<MultisigRequestId> = self
+            .delete_voted_requests
+            .get(&old_member)
+            .map(|request_ids| request_ids.iter().copied().collect())
+            .unwrap_or_default();
+        for request_id in voted_ids {
+            remove_member_request(&mut self.delete_voted_requests, &old_member, request_id);
+            insert_member_request(
+                &mut self.delete_voted_requests,
+                new_member.clone(),
+                request_id,
+                StorageKey::MemberDeleteVotedRequests {
+                    member: new_member.clone(),
+                },
+            );
+            let votes = self.delete_votes.get_mut(&request_id).unwrap();
+            for vote in votes.iter_mut().filter(|vote| **vote == old_member) {
+                *vote = new_member.clone();
+            }
+        }
+
+        if self.guardians.remove(old) {
+            self.guardians.insert(new.clone());
+        }
+        self.revoked_keys.insert(old.clone());
+        self.revoked_keys.remove(new);
+        self.signer_keys.remove(old);
+        self.signer_keys.insert(new.clone());
+        self.key_permissions.remove(old);
+        self.key_permissions.insert(new.clone(), permission.clone());
+        self.last_activity.remove(&old_member);
+        self.record_activity(&new_member);
+        permission
+    }
+}
+
+// Permission of keys that can only call the signer methods of the multisig - used for keys added by the multisig
+pub(crate) fn signer_permission(allowance: Option<U128>) -> FunctionCallPermission {
+    FunctionCallPermission {
+        allowance,
+        receiver_id: env::current_account_id(),
+        method_names: SIGNER_METHODS.iter().map(ToString::to_string).collect(),
+    }
+}
+
+pub(crate) fn add_function_call_key(
+    promise: Promise,
+    public_key: PublicKey,
+    permission: FunctionCallPermission,
+) -> Promise {
+    promise.add_access_key_allowance(
+        public_key,
+        key_allowance(permission.allowance),
+        permission.receiver_id,
+        permission.method_names.join(","),
+    )
+}
+
+// Allowance of a function call key, unlimited if not set or zero like in the protocol
+fn key_allowance(allowance: Option<U128>) -> Allowance {
+    allowance
+        .and_then(|allowance| Allowance::limited(NearToken::from_yoctonear(allowance.0)))
+        .unwrap_or(Allowance::Unlimited)
+}
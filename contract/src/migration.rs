@@ -0,0 +1,205 @@
+use std::collections::{BTreeMap, HashSet};
+
+use multisig_model::{
+    ConfirmationsPolicy, Member, MultiSigRequest, MultiSigRequestWithSigner, MultisigRequestId, RetryConfig,
+};
+use near_sdk::{
+    borsh::BorshDeserialize,
+    collections::UnorderedMap as LegacyUnorderedMap,
+    env, near, near_bindgen,
+    store::{IterableMap, IterableSet, LazyOption, LookupMap, LookupSet, Vector},
+    PublicKey,
+};
+
+use crate::{insert_member_request, MultiSigContract, MultiSigContractExt, StorageKey, REQUEST_COOLDOWN};
+
+const STATE_KEY: &[u8] = b"STATE";
+
+// State layout of the multisig before the move to `near_sdk::store` collections
+#[near(serializers=[borsh])]
+struct MultiSigContractV1 {
+    num_confirmations: usize,
+    request_nonce: MultisigRequestId,
+    requests: LegacyUnorderedMap<MultisigRequestId, MultiSigRequestWithSignerV1>,
+    confirmations: LegacyUnorderedMap<MultisigRequestId, HashSet<PublicKey>>,
+    num_requests_pk: LegacyUnorderedMap<PublicKey, u32>,
+    active_requests_limit: u32,
+}
+
+// Requests were added only by keys before member accounts
+#[near(serializers=[borsh])]
+struct MultiSigRequestWithSignerV1 {
+    request: MultiSigRequest,
+    signer_pk: PublicKey,
+    added_timestamp: u64,
+}
+
+#[near_bindgen]
+impl MultiSigContract {
+    /// Migrates the state after `UpgradeSelf` deployed new code.
+    /// Keeps the state as is if it already has the current layout.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = env::storage_read(STATE_KEY).expect("Contract is not initialized");
+
+        if let Ok(contract) = Self::try_from_slice(&state) {
+            return contract;
+        }
+
+        let mut old = MultiSigContractV1::try_from_slice(&state).expect("Failed to deserialize old state");
+
+        let mut requests = IterableMap::new(StorageKey::Requests);
+        let mut confirmations = LookupMap::new(StorageKey::Confirmations);
+        let mut requests_by_signer = LookupMap::new(StorageKey::RequestsBySigner);
+        let mut confirmed_requests = LookupMap::new(StorageKey::ConfirmedRequests);
+        // keys that added or confirmed pending requests are known signers
+        let mut signer_keys = IterableSet::new(StorageKey::SignerKeys);
+        for (request_id, request) in &old.requests {
+            let mut request_confirmations = IterableSet::new(StorageKey::RequestConfirmations { request_id });
+            for public_key in old.confirmations.get(&request_id).unwrap_or_default() {
+                signer_keys.insert(public_key.clone());
+                let member = Member::Key(public_key);
+                insert_member_request(
+                    &mut confirmed_requests,
+                    member.clone(),
+                    request_id,
+                    StorageKey::MemberConfirmedRequests { member: member.clone() },
+                );
+                request_confirmations.insert(member);
+            }
+            confirmations.insert(request_id, request_confirmations);
+            signer_keys.insert(request.signer_pk.clone());
+            let signer = Member::Key(request.signer_pk);
+            insert_member_request(
+                &mut requests_by_signer,
+                signer.clone(),
+                request_id,
+                StorageKey::SignerRequests { member: signer.clone() },
+            );
+            requests.insert(
+                request_id,
+                MultiSigRequestWithSigner {
+                    request: request.request,
+                    signer,
+                    added_timestamp: request.added_timestamp,
+                },
+            );
+        }
+
+        old.requests.clear();
+        old.confirmations.clear();
+        old.num_requests_pk.clear();
+
+        Self {
+            num_confirmations: old.num_confirmations,
+            request_nonce: old.request_nonce,
+            requests,
+            confirmations,
+            requests_by_signer,
+            confirmed_requests,
+            revoked_keys: LookupSet::new(StorageKey::RevokedKeys),
+            active_requests_limit: old.active_requests_limit,
+            request_cooldown: REQUEST_COOLDOWN,
+            cooldown_overrides: BTreeMap::new(),
+            request_expiry: None,
+            confirmations_policy: ConfirmationsPolicy::default(),
+            elevated_num_confirmations: old.num_confirmations,
+            code_hash: None,
+            upgrades: Vector::new(StorageKey::Upgrades),
+            staged_code: LookupMap::new(StorageKey::StagedCode),
+            staged_chunks: LookupMap::new(StorageKey::StagedChunks),
+            staged_sizes: LookupMap::new(StorageKey::StagedSizes),
+            spending_limits: LookupMap::new(StorageKey::SpendingLimits),
+            spendings: LookupMap::new(StorageKey::Spendings),
+            policy: LazyOption::new(StorageKey::Policy, None),
+            guardians: IterableSet::new(StorageKey::Guardians),
+            paused: None,
+            recovery: None,
+            recovery_proposal: None,
+            schedules: IterableMap::new(StorageKey::Schedules),
+            schedule_nonce: 0,
+            streams: IterableMap::new(StorageKey::Streams),
+            stream_nonce: 0,
+            dependencies: LookupMap::new(StorageKey::Dependencies),
+            dependents: LookupMap::new(StorageKey::Dependents),
+            history: Vector::new(StorageKey::History),
+            executing: LookupMap::new(StorageKey::Executing),
+            failed_requests: IterableMap::new(StorageKey::FailedRequests),
+            retry_config: RetryConfig::default(),
+            execution_results: LookupMap::new(StorageKey::ExecutionResults),
+            members: IterableSet::new(StorageKey::Members),
+            delete_votes: LookupMap::new(StorageKey::DeleteVotes),
+            delete_voted_requests: LookupMap::new(StorageKey::DeleteVotedRequests),
+            signer_keys,
+            // keys that never added or confirmed a pending request are unknown
+            signer_keys_complete: false,
+            key_permissions: LookupMap::new(StorageKey::KeyPermissions),
+            last_activity: LookupMap::new(StorageKey::LastActivity),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, str::FromStr};
+
+    use multisig_model::{Member, MultiSigRequest, MultiSigRequestAction, MultisigView};
+    use near_sdk::{
+        collections::UnorderedMap as LegacyUnorderedMap,
+        env,
+        test_utils::{
+            test_env::{alice, bob},
+            VMContextBuilder,
+        },
+        testing_env, NearToken, PublicKey,
+    };
+
+    use super::{MultiSigContractV1, MultiSigRequestWithSignerV1};
+    use crate::MultiSigContract;
+
+    #[test]
+    fn migrate_from_legacy_collections() {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(alice())
+            .predecessor_account_id(alice())
+            .build());
+        let key = PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap();
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: NearToken::from_yoctonear(1_000),
+            }],
+            depends_on: vec![],
+        };
+        let mut old = MultiSigContractV1 {
+            num_confirmations: 2,
+            request_nonce: 1,
+            requests: LegacyUnorderedMap::new(b"r".to_vec()),
+            confirmations: LegacyUnorderedMap::new(b"c".to_vec()),
+            num_requests_pk: LegacyUnorderedMap::new(b"k".to_vec()),
+            active_requests_limit: 12,
+        };
+        old.requests.insert(
+            &0,
+            &MultiSigRequestWithSignerV1 {
+                request: request.clone(),
+                signer_pk: key.clone(),
+                added_timestamp: 0,
+            },
+        );
+        old.confirmations.insert(&0, &HashSet::from([key.clone()]));
+        old.num_requests_pk.insert(&key, &1);
+        env::state_write(&old);
+
+        let contract = MultiSigContract::migrate();
+
+        assert_eq!(contract.get_num_confirmations(), 2);
+        assert_eq!(contract.get_request_nonce(), 1);
+        assert_eq!(contract.list_request_ids(), vec![0]);
+        assert_eq!(contract.get_request(0), request);
+        assert_eq!(contract.get_confirmations(0), vec![Member::Key(key.clone())]);
+        assert_eq!(contract.get_num_requests_pk(key.clone()), 1);
+        assert_eq!(contract.list_requests_by_signer(Member::Key(key)), vec![0]);
+    }
+}
@@ -0,0 +1,18 @@
+use multisig_model::{MultiSigRequest, MultiSigRequestAction};
+
+use crate::MultiSigContract;
+
+impl MultiSigContract {
+    // Only Unpause can be added, confirmed and executed while paused - used in add_request, confirm and execute_request
+    pub(crate) fn assert_not_paused(&self, request: &MultiSigRequest) {
+        assert!(
+            !self.is_paused_for(request),
+            "Multisig is paused, only Unpause request is allowed"
+        );
+    }
+
+    // used in list_ready_requests
+    pub(crate) fn is_paused_for(&self, request: &MultiSigRequest) -> bool {
+        self.paused.is_some() && !matches!(request.actions.as_slice(), [MultiSigRequestAction::Unpause { .. }])
+    }
+}
@@ -0,0 +1,93 @@
+use multisig_model::{MultiSigRequest, MultiSigRequestAction, MultiSigRequestActionKind, Policy};
+use near_sdk::env;
+
+use crate::MultiSigContract;
+
+impl MultiSigContract {
+    // Prevents requests the policy doesn't allow - used in add_request and execute_request
+    pub(crate) fn assert_allowed_by_policy(&self, request: &MultiSigRequest) {
+        if let Some(policy) = self.policy.get() {
+            assert_allowed(policy, request);
+        }
+    }
+}
+
+// Prevents the policy from locking itself - used when SetPolicy is added and executed
+pub(crate) fn assert_valid_policy(policy: &Policy) {
+    assert!(
+        !policy.forbidden_actions.contains(&MultiSigRequestActionKind::SetPolicy),
+        "SetPolicy can not be forbidden"
+    );
+}
+
+fn assert_allowed(policy: &Policy, request: &MultiSigRequest) {
+    if let Some(allowed_receivers) = &policy.allowed_receivers {
+        assert!(
+            request.receiver_id == env::current_account_id() || allowed_receivers.contains(&request.receiver_id),
+            "Receiver {} is not allowed by the policy",
+            request.receiver_id
+        );
+    }
+
+    for action in &request.actions {
+        let kind = action.kind();
+        assert!(
+            !policy.forbidden_actions.contains(&kind),
+            "{kind:?} is forbidden by the policy"
+        );
+        // a configuration update is forbidden along with the setters making the same changes
+        if let MultiSigRequestAction::UpdateConfig { update } = action {
+            for kind in update.setter_kinds() {
+                assert!(
+                    !policy.forbidden_actions.contains(&kind),
+                    "{kind:?} is forbidden by the policy"
+                );
+            }
+        }
+        // streams pay out to their receivers directly
+        if let MultiSigRequestAction::CreateStream { receiver_id, .. } = action {
+            if let Some(allowed_receivers) = &policy.allowed_receivers {
+                assert!(
+                    allowed_receivers.contains(receiver_id),
+                    "Receiver {receiver_id} is not allowed by the policy"
+                );
+            }
+        }
+        // runs of the schedule are checked when triggered as well
+        if let MultiSigRequestAction::Schedule { request, .. } = action {
+            assert_allowed(policy, request);
+            continue;
+        }
+        if let MultiSigRequestAction::Batch { transactions } = action {
+            for transaction in transactions {
+                assert_allowed(policy, transaction);
+            }
+            continue;
+        }
+
+        let MultiSigRequestAction::FunctionCall {
+            method_name,
+            deposit,
+            gas,
+            ..
+        } = action
+        else {
+            continue;
+        };
+        if let Some(allowed_methods) = policy.allowed_methods.get(&request.receiver_id) {
+            assert!(
+                allowed_methods.contains(method_name),
+                "Method {method_name} is not allowed by the policy"
+            );
+        }
+        if let Some(max_deposit) = policy.max_deposit {
+            assert!(
+                *deposit <= max_deposit,
+                "Deposit exceeds the maximum allowed by the policy"
+            );
+        }
+        if let Some(max_gas) = policy.max_gas {
+            assert!(*gas <= max_gas, "Gas exceeds the maximum allowed by the policy");
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use multisig_model::{Member, RecoveryConfig, RecoveryProposal};
+use near_sdk::{env, is_promise_success, near_bindgen, Gas, Promise, PublicKey};
+
+use crate::{
+    event::MultisigEvent,
+    member::{add_function_call_key, signer_permission},
+    MultiSigContract, MultiSigContractExt,
+};
+
+/// Minimal time members have to cancel a recovery proposal, 1 day in nanoseconds.
+const MIN_RECOVERY_DELAY: u64 = 86_400_000_000_000;
+
+const ON_RECOVERY_EXECUTED_GAS: Gas = Gas::from_tgas(30);
+
+// Rejects recovery that members can't react to in time - used in add_request
+pub(crate) fn assert_valid_recovery(recovery: &RecoveryConfig) {
+    assert!(
+        recovery.delay.0 >= MIN_RECOVERY_DELAY,
+        "Recovery delay must be at least {MIN_RECOVERY_DELAY} nanoseconds"
+    );
+}
+
+// Rejects a key that is both added and removed - used in propose_recovery
+pub(crate) fn assert_valid_proposal(keys: &[PublicKey], removed_keys: &[PublicKey]) {
+    assert!(
+        keys.iter().all(|public_key| !removed_keys.contains(public_key)),
+        "Proposed keys can not be removed at the same time"
+    );
+}
+
+impl MultiSigContract {
+    // Recovery replaces lost keys, not the ones still in use - used in propose_recovery
+    pub(crate) fn assert_inactive_keys(&self, removed_keys: &[PublicKey], inactivity_period: u64) {
+        assert!(!removed_keys.is_empty(), "Recovery must remove the inactive keys");
+        for public_key in removed_keys {
+            let last_activity = self
+                .last_activity
+                .get(&Member::Key(public_key.clone()))
+                .copied()
+                .unwrap_or_default();
+            assert!(
+                env::block_timestamp() >= last_activity.saturating_add(inactivity_period),
+                "Key {} was active within the inactivity period",
+                String::from(public_key)
+            );
+        }
+    }
+}
+
+// Swaps the access keys of the proposal, the signers are replaced once it succeeded - used in execute_recovery
+pub(crate) fn apply_recovery(proposal: RecoveryProposal) -> Promise {
+    let mut promise = Promise::new(env::current_account_id());
+    for public_key in &proposal.removed_keys {
+        promise = promise.delete_key(public_key.clone());
+    }
+    for public_key in &proposal.keys {
+        promise = add_function_call_key(promise, public_key.clone(), signer_permission(None));
+    }
+    promise.then(
+        MultiSigContract::ext(env::current_account_id())
+            .with_static_gas(ON_RECOVERY_EXECUTED_GAS)
+            .on_recovery_executed(proposal),
+    )
+}
+
+#[near_bindgen]
+impl MultiSigContract {
+    /// Replaces the signers and the threshold with the proposed ones if the access keys were swapped.
+    #[private]
+    pub fn on_recovery_executed(&mut self, proposal: RecoveryProposal) {
+        if !is_promise_success() {
+            env::log_str("Recovery failed, the access keys were not changed");
+            return;
+        }
+        for public_key in proposal.removed_keys {
+            self.remove_signer(&Member::Key(public_key));
+        }
+        for public_key in proposal.keys {
+            self.revoked_keys.remove(&public_key);
+            self.signer_keys.insert(public_key.clone());
+            self.set_key_permission(&public_key, Some(signer_permission(None)));
+        }
+        // the old elevated threshold might not be reachable by the new signers
+        self.num_confirmations = proposal.num_confirmations;
+        self.elevated_num_confirmations = proposal.num_confirmations;
+        self.invalidate_confirmations(None);
+        MultisigEvent::RecoveryExecuted {
+            proposer: proposal.proposer,
+        }
+        .emit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use multisig_model::{Member, MultiSigRequest, MultiSigRequestAction, MultisigApi, MultisigView, RecoveryConfig};
+    use near_sdk::{
+        test_utils::{
+            test_env::{alice, bob},
+            VMContextBuilder,
+        },
+        testing_env, AccountId, NearToken, PromiseResult, PublicKey, VMContext,
+    };
+
+    use crate::MultiSigContract;
+
+    const DELAY: u64 = 30 * 86_400_000_000_000;
+
+    const INACTIVITY_PERIOD: u64 = 90 * 86_400_000_000_000;
+
+    // the member key was last active when the recovery was set at 0
+    const PROPOSED_AT: u64 = INACTIVITY_PERIOD;
+
+    fn member() -> PublicKey {
+        PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap()
+    }
+
+    fn new_key() -> PublicKey {
+        PublicKey::from_str("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R").unwrap()
+    }
+
+    fn recovery_account() -> AccountId {
+        AccountId::from_str("recovery").unwrap()
+    }
+
+    fn context(predecessor: AccountId, timestamp: u64) -> VMContext {
+        VMContextBuilder::new()
+            .current_account_id(alice())
+            .predecessor_account_id(predecessor)
+            .signer_account_pk(member())
+            .account_balance(NearToken::from_near(10))
+            .block_timestamp(timestamp)
+            .build()
+    }
+
+    fn set_promise_result(result: PromiseResult) {
+        testing_env!(
+            context(alice(), PROPOSED_AT + DELAY),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+    }
+
+    fn contract_with_recovery() -> MultiSigContract {
+        testing_env!(context(alice(), 0));
+        let mut c = MultiSigContract::new(1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetRecovery {
+                recovery: Some(RecoveryConfig {
+                    accounts: vec![recovery_account()],
+                    delay: DELAY.into(),
+                    inactivity_period: INACTIVITY_PERIOD.into(),
+                }),
+            }],
+            depends_on: vec![],
+        });
+        testing_env!(context(recovery_account(), PROPOSED_AT));
+        c.propose_recovery(vec![new_key()], vec![member()], 1);
+        c
+    }
+
+    #[test]
+    fn test_execute_recovery_after_delay() {
+        let mut c = contract_with_recovery();
+        testing_env!(context(alice(), PROPOSED_AT));
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: NearToken::from_yoctonear(1),
+            }],
+            depends_on: vec![],
+        });
+        let proposal = c.get_recovery_proposal().unwrap();
+        assert_eq!(proposal.proposer, recovery_account());
+        assert_eq!(proposal.executable_at, PROPOSED_AT + DELAY);
+
+        testing_env!(context(bob(), PROPOSED_AT + DELAY));
+        c.execute_recovery();
+        assert!(c.get_recovery_proposal().is_none());
+        // signers are replaced only once the keys are swapped
+        assert_eq!(c.get_num_requests_pk(member()), 1);
+
+        set_promise_result(PromiseResult::Successful(vec![]));
+        c.on_recovery_executed(proposal);
+        assert_eq!(c.get_num_confirmations(), 1);
+        // requests of the removed key are deleted
+        assert_eq!(c.get_num_requests_pk(member()), 0);
+        assert!(c.list_request_ids().is_empty());
+    }
+
+    #[test]
+    fn test_failed_recovery_keeps_signers() {
+        let mut c = contract_with_recovery();
+        testing_env!(context(alice(), PROPOSED_AT));
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: NearToken::from_yoctonear(1),
+            }],
+            depends_on: vec![],
+        });
+        let proposal = c.get_recovery_proposal().unwrap();
+        testing_env!(context(bob(), PROPOSED_AT + DELAY));
+        c.execute_recovery();
+
+        set_promise_result(PromiseResult::Failed);
+        c.on_recovery_executed(proposal);
+        assert_eq!(c.get_num_requests_pk(member()), 1);
+        assert!(!c.list_signer_keys().contains(&new_key()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Proposed keys can not be removed at the same time")]
+    fn test_panics_propose_recovery_overlapping_keys() {
+        let mut c = contract_with_recovery();
+        testing_env!(context(alice(), PROPOSED_AT));
+        c.cancel_recovery();
+        testing_env!(context(recovery_account(), PROPOSED_AT));
+        c.propose_recovery(vec![new_key()], vec![new_key()], 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "was active within the inactivity period")]
+    fn test_panics_propose_recovery_of_active_key() {
+        let mut c = contract_with_recovery();
+        testing_env!(context(alice(), PROPOSED_AT));
+        c.cancel_recovery();
+        assert_eq!(c.get_last_activity(Member::Key(member())), Some(PROPOSED_AT));
+        testing_env!(context(recovery_account(), PROPOSED_AT + INACTIVITY_PERIOD - 1));
+        c.propose_recovery(vec![new_key()], vec![member()], 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Recovery must remove the inactive keys")]
+    fn test_panics_propose_recovery_without_removed_keys() {
+        let mut c = contract_with_recovery();
+        testing_env!(context(alice(), PROPOSED_AT));
+        c.cancel_recovery();
+        testing_env!(context(recovery_account(), PROPOSED_AT));
+        c.propose_recovery(vec![new_key()], vec![], 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Multisig is paused")]
+    fn test_panics_execute_recovery_while_paused() {
+        let mut c = contract_with_recovery();
+        testing_env!(context(alice(), PROPOSED_AT));
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetGuardians {
+                guardians: vec![member()],
+            }],
+            depends_on: vec![],
+        });
+        c.pause();
+        testing_env!(context(recovery_account(), PROPOSED_AT + DELAY));
+        c.execute_recovery();
+    }
+
+    #[test]
+    #[should_panic(expected = "Recovery delay must be at least")]
+    fn test_panics_set_recovery_without_delay() {
+        testing_env!(context(alice(), 0));
+        let mut c = MultiSigContract::new(1);
+        c.add_request(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetRecovery {
+                recovery: Some(RecoveryConfig {
+                    accounts: vec![recovery_account()],
+                    delay: 0.into(),
+                    inactivity_period: INACTIVITY_PERIOD.into(),
+                }),
+            }],
+            depends_on: vec![],
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Recovery delay is not over yet")]
+    fn test_panics_execute_recovery_before_delay() {
+        let mut c = contract_with_recovery();
+        testing_env!(context(recovery_account(), PROPOSED_AT + DELAY - 1));
+        c.execute_recovery();
+    }
+
+    #[test]
+    #[should_panic(expected = "No recovery proposal")]
+    fn test_cancel_recovery() {
+        let mut c = contract_with_recovery();
+        testing_env!(context(alice(), PROPOSED_AT + DELAY / 2));
+        c.cancel_recovery();
+        assert!(c.get_recovery_proposal().is_none());
+
+        testing_env!(context(recovery_account(), PROPOSED_AT + DELAY));
+        c.execute_recovery();
+    }
+
+    #[test]
+    #[should_panic(expected = "Only recovery accounts can propose recovery")]
+    fn test_panics_propose_recovery_not_recovery_account() {
+        let mut c = contract_with_recovery();
+        testing_env!(context(alice(), PROPOSED_AT));
+        c.cancel_recovery();
+        testing_env!(context(bob(), PROPOSED_AT));
+        c.propose_recovery(vec![new_key()], vec![], 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Predecessor account must much current account")]
+    fn test_panics_cancel_recovery_not_member() {
+        let mut c = contract_with_recovery();
+        testing_env!(context(recovery_account(), PROPOSED_AT));
+        c.cancel_recovery();
+    }
+}
@@ -0,0 +1,127 @@
+use multisig_model::{FailedRequest, Member, MultiSigRequest, MultiSigRequestAction, MultisigRequestId};
+use near_sdk::{env, near, Gas, PromiseOrValue};
+
+use crate::{allowance::AllowanceSpending, MultiSigContract};
+
+/// Request being executed, kept until its outcome is known.
+#[near(serializers=[borsh])]
+pub(crate) struct ExecutingRequest {
+    pub(crate) request: MultiSigRequest,
+    pub(crate) signer: Member,
+    pub(crate) confirmations: Vec<Member>,
+    pub(crate) attempts: u32,
+    // allowance spent when the request was executed with a single confirmation
+    pub(crate) spent_allowance: Vec<AllowanceSpending>,
+    // success of each transaction of a batch that failed before, only the failed ones are executed again
+    pub(crate) batch_results: Option<Vec<bool>>,
+}
+
+impl ExecutingRequest {
+    // The request to execute, a retried batch without the transactions that succeeded before
+    pub(crate) fn pending_request(&self) -> MultiSigRequest {
+        let mut request = self.request.clone();
+        if let (Some(results), [MultiSigRequestAction::Batch { transactions }]) =
+            (&self.batch_results, request.actions.as_mut_slice())
+        {
+            let mut results = results.iter();
+            transactions.retain(|_| !results.next().copied().unwrap_or_default());
+        }
+        request
+    }
+
+    // Success of each transaction of the batch, with the outcomes of the executed ones in place of the failed ones
+    pub(crate) fn batch_results(&self, mut executed: impl Iterator<Item = bool>) -> Vec<bool> {
+        match &self.batch_results {
+            Some(results) => results
+                .iter()
+                .map(|is_success| *is_success || executed.next().unwrap_or_default())
+                .collect(),
+            None => executed.collect(),
+        }
+    }
+}
+
+impl MultiSigContract {
+    // Executes the failed request again - used in retry
+    pub(crate) fn retry_request(&mut self, request_id: MultisigRequestId, gas: Option<Gas>) -> PromiseOrValue<()> {
+        self.assert_member();
+        let failed = self
+            .failed_requests
+            .remove(&request_id)
+            .expect("No such failed request");
+        assert!(
+            failed.attempts < self.retry_config.max_attempts,
+            "No retry attempts left"
+        );
+        assert!(
+            env::block_timestamp() <= failed.failed_at.saturating_add(self.retry_config.window.0),
+            "Retry window is over"
+        );
+        // confirmations might have been dropped or the threshold raised since the request failed
+        assert!(
+            failed.confirmations.len() >= self.required_confirmations(&failed.request),
+            "Not enough confirmations left to retry, the request must be added again"
+        );
+        let mut request = failed.request;
+        if let Some(gas) = gas {
+            raise_gas(&mut request, gas);
+        }
+        self.remove_delete_votes(request_id);
+        self.execute_request(
+            request_id,
+            ExecutingRequest {
+                request,
+                signer: failed.signer,
+                confirmations: failed.confirmations,
+                attempts: failed.attempts + 1,
+                spent_allowance: vec![],
+                batch_results: failed.batch_results,
+            },
+        )
+    }
+
+    // Keeps the request to be retried, requests depending on it wait unless it can't be retried anymore
+    // - used when the execution fails
+    pub(crate) fn fail_request(&mut self, request_id: MultisigRequestId) {
+        let Some(executing) = self.executing.remove(&request_id) else {
+            self.cancel_dependents(request_id);
+            return;
+        };
+        self.refund_allowance(executing.spent_allowance);
+        if executing.attempts >= self.retry_config.max_attempts {
+            self.cancel_dependents(request_id);
+        }
+        self.failed_requests.insert(
+            request_id,
+            FailedRequest {
+                request: executing.request,
+                signer: executing.signer,
+                confirmations: executing.confirmations,
+                attempts: executing.attempts,
+                failed_at: env::block_timestamp(),
+                batch_results: executing.batch_results,
+            },
+        );
+    }
+
+    // Removes the failed request along with requests depending on it - used in delete_request
+    pub(crate) fn delete_failed_request(&mut self, request_id: MultisigRequestId) -> MultiSigRequest {
+        let failed = self
+            .failed_requests
+            .remove(&request_id)
+            .expect("No such failed request");
+        self.remove_delete_votes(request_id);
+        self.unstage_request_code(&failed.request);
+        self.cancel_dependents(request_id);
+        failed.request
+    }
+}
+
+// Function calls get at least the given gas, e.g. when the previous attempt ran out of gas
+fn raise_gas(request: &mut MultiSigRequest, gas: Gas) {
+    for action in &mut request.actions {
+        if let MultiSigRequestAction::FunctionCall { gas: action_gas, .. } = action {
+            *action_gas = (*action_gas).max(gas);
+        }
+    }
+}
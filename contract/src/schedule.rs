@@ -0,0 +1,93 @@
+use multisig_model::{MultiSigRequest, MultisigRequestId, ScheduleId, ScheduledRequest, ScheduledRun};
+use near_sdk::{env, is_promise_success, near_bindgen, Gas, Promise};
+
+use crate::{
+    event::MultisigEvent,
+    execution::{assert_valid_transaction, transaction_promise},
+    MultiSigContract, MultiSigContractExt,
+};
+
+const ON_SCHEDULED_RUN_GAS: Gas = Gas::from_tgas(10);
+
+impl MultiSigContract {
+    // Stores the schedule created by the request - used in execute_single_action
+    pub(crate) fn add_schedule(&mut self, request_id: MultisigRequestId, schedule: ScheduledRequest) {
+        let schedule_id = self.schedule_nonce;
+        self.schedules.insert(schedule_id, schedule);
+        self.schedule_nonce += 1;
+        MultisigEvent::ScheduleCreated {
+            schedule_id,
+            request_id,
+        }
+        .emit();
+    }
+
+    // Executes the next due run of the schedule - used in trigger_scheduled
+    pub(crate) fn trigger_schedule(&mut self, schedule_id: ScheduleId) -> Promise {
+        let schedule = self.schedules.get(&schedule_id).expect("No such schedule");
+        assert!(schedule.remaining > 0, "Schedule is completed");
+        let now = env::block_timestamp();
+        assert!(now >= next_run(schedule), "Next run is not due yet");
+        let request = schedule.request.clone();
+        // the multisig might have been paused or the policy changed since the schedule was added
+        self.assert_not_paused(&request);
+        self.assert_allowed_by_policy(&request);
+
+        let schedule = self.schedules.get_mut(&schedule_id).unwrap();
+        schedule.remaining -= 1;
+        let run = u32::try_from(schedule.history.len()).unwrap();
+        schedule.history.push(ScheduledRun {
+            timestamp: now,
+            succeeded: None,
+        });
+        transaction_promise(&request).then(
+            MultiSigContract::ext(env::current_account_id())
+                .with_static_gas(ON_SCHEDULED_RUN_GAS)
+                .on_scheduled_run(schedule_id, run),
+        )
+    }
+}
+
+#[near_bindgen]
+impl MultiSigContract {
+    /// Records the outcome of the run, removes the schedule once its last run finished.
+    #[private]
+    pub fn on_scheduled_run(&mut self, schedule_id: ScheduleId, run: u32) {
+        // the schedule might have been cancelled while the run was in flight
+        let Some(schedule) = self.schedules.get_mut(&schedule_id) else {
+            return;
+        };
+        let succeeded = is_promise_success();
+        if !succeeded {
+            env::log_str(&format!("Run {run} of schedule {schedule_id} failed"));
+        }
+        schedule.history[run as usize].succeeded = Some(succeeded);
+        if schedule.remaining > 0 {
+            return;
+        }
+        let results: Option<Vec<bool>> = schedule.history.iter().map(|run| run.succeeded).collect();
+        if let Some(results) = results {
+            self.schedules.remove(&schedule_id);
+            MultisigEvent::ScheduleCompleted { schedule_id, results }.emit();
+        }
+    }
+}
+
+// Rejects schedules that can never run - used in add_request
+pub(crate) fn assert_valid_schedule(request: &MultiSigRequest, interval: u64, count: u32) {
+    assert!(count > 0, "Schedule must have at least one run");
+    assert!(
+        interval > 0 || count == 1,
+        "Interval must be positive for more than one run"
+    );
+    assert_valid_transaction(request, "Only Transfer and FunctionCall can be scheduled");
+}
+
+// Time of the run following the ones triggered so far
+fn next_run(schedule: &ScheduledRequest) -> u64 {
+    let runs = schedule.history.len() as u64;
+    schedule
+        .start
+        .0
+        .saturating_add(schedule.interval.0.saturating_mul(runs))
+}
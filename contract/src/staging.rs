@@ -0,0 +1,181 @@
+use multisig_model::{Member, MultiSigRequest, MultiSigRequestAction, StagedCodeInfo};
+use near_sdk::{env, near, CryptoHash};
+
+use crate::MultiSigContract;
+
+/// Largest code that can be staged, the contract size limit of the protocol.
+const MAX_STAGED_CODE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Limit of all code staged by a member at once, so uploads can't drain the balance of the multisig through storage
+/// and a single member can't use up the space of the others.
+const MAX_MEMBER_STAGED_SIZE: u64 = 2 * MAX_STAGED_CODE_SIZE;
+
+/// Code uploaded with `stage_code`, its bytes are stored in separate chunks.
+#[near(serializers=[borsh])]
+pub(crate) struct StagedCode {
+    // member appending the chunks, only they can unstage the code while no request deploys it
+    uploader: Member,
+    // declared with the first chunk and reserved from the limit of the uploader
+    code_size: u64,
+    // uploaded so far
+    size: u64,
+    num_chunks: u32,
+    // set once the uploaded bytes reach the declared size and match the hash, no chunks can be added after that
+    complete: bool,
+    // requests deploying the code, it is freed when the last of them is deployed or removed
+    num_requests: u32,
+}
+
+impl MultiSigContract {
+    // Stores the chunk after the ones uploaded so far and returns the size of the code. The hash is checked once
+    // the upload reaches the declared size - used in stage_code
+    pub(crate) fn append_staged_chunk(
+        &mut self,
+        member: Member,
+        code_hash: CryptoHash,
+        code_size: u64,
+        chunk: Vec<u8>,
+    ) -> u64 {
+        if !self.staged_code.contains_key(&code_hash) {
+            self.start_upload(member.clone(), code_hash, code_size);
+        }
+        let staged = self.staged_code.get_mut(&code_hash).unwrap();
+        assert!(!staged.complete, "Code is already staged");
+        assert!(staged.uploader == member, "Code is being staged by another member");
+        assert_eq!(
+            staged.code_size, code_size,
+            "Code size differs from the size of the upload"
+        );
+        let chunk_size = chunk.len() as u64;
+        assert!(
+            staged.size + chunk_size <= staged.code_size,
+            "Chunk exceeds the size of the code"
+        );
+        let index = staged.num_chunks;
+        staged.num_chunks += 1;
+        staged.size += chunk_size;
+        let size = staged.size;
+        self.staged_chunks.insert((code_hash, index), chunk);
+        if size == code_size {
+            assert!(
+                env::sha256_array(self.staged_bytes(code_hash)) == code_hash,
+                "Staged code doesn't match the hash"
+            );
+            self.staged_code.get_mut(&code_hash).unwrap().complete = true;
+        }
+        size
+    }
+
+    // Removes code nothing references, e.g. an abandoned upload. Other members can only remove it once the uploader
+    // is no longer a member - used in unstage_code
+    pub(crate) fn unstage(&mut self, member: Member, code_hash: CryptoHash) {
+        let staged = self.staged_code.get(&code_hash).expect("No such staged code");
+        assert_eq!(staged.num_requests, 0, "Staged code is used by pending requests");
+        let is_uploader_removed = match &staged.uploader {
+            Member::Key(public_key) => self.revoked_keys.contains(public_key),
+            Member::Account(account_id) => !self.members.contains(account_id),
+        };
+        assert!(
+            staged.uploader == member || is_uploader_removed,
+            "Only the uploader can unstage the code"
+        );
+        self.remove_staged_code(code_hash);
+    }
+
+    pub(crate) fn staged_code_info(&self, code_hash: CryptoHash) -> Option<StagedCodeInfo> {
+        self.staged_code.get(&code_hash).map(|staged| StagedCodeInfo {
+            code_size: staged.code_size,
+            size: staged.size,
+            complete: staged.complete,
+        })
+    }
+
+    // Returns the staged code, it is kept until the deployment succeeds so a failed request can be retried
+    // - used when the request is executed
+    pub(crate) fn staged_code_bytes(&self, code_hash: CryptoHash) -> Vec<u8> {
+        let staged = self.staged_code.get(&code_hash).expect("No such staged code");
+        assert!(staged.complete, "Staged code is incomplete");
+        self.staged_bytes(code_hash)
+    }
+
+    // Frees the code staged for the request - used when the request succeeds or is removed
+    pub(crate) fn unstage_request_code(&mut self, request: &MultiSigRequest) {
+        for action in &request.actions {
+            if let MultiSigRequestAction::DeployStagedCode { code_hash } = action {
+                self.release_staged_code((*code_hash).into());
+            }
+        }
+    }
+
+    // Prevents requests from referencing code that is not fully uploaded and keeps the code until the request is
+    // done with it - used in add_request
+    pub(crate) fn reference_staged_code(&mut self, request: &MultiSigRequest) {
+        for action in &request.actions {
+            if let MultiSigRequestAction::DeployStagedCode { code_hash } = action {
+                let code_hash: CryptoHash = (*code_hash).into();
+                let staged = self.staged_code.get_mut(&code_hash).expect("No such staged code");
+                assert!(staged.complete, "Staged code is incomplete");
+                staged.num_requests += 1;
+            }
+        }
+    }
+
+    fn release_staged_code(&mut self, code_hash: CryptoHash) {
+        let Some(staged) = self.staged_code.get_mut(&code_hash) else {
+            return;
+        };
+        staged.num_requests = staged.num_requests.saturating_sub(1);
+        if staged.num_requests == 0 {
+            self.remove_staged_code(code_hash);
+        }
+    }
+
+    // Reserves the size of the code from the limit of the uploader
+    fn start_upload(&mut self, uploader: Member, code_hash: CryptoHash, code_size: u64) {
+        assert!(code_size > 0, "Code size must be positive");
+        assert!(
+            code_size <= MAX_STAGED_CODE_SIZE,
+            "Staged code exceeds the limit of {MAX_STAGED_CODE_SIZE} bytes"
+        );
+        let staged_size = self.staged_sizes.get(&uploader).copied().unwrap_or_default() + code_size;
+        assert!(
+            staged_size <= MAX_MEMBER_STAGED_SIZE,
+            "Code staged by the member exceeds the limit of {MAX_MEMBER_STAGED_SIZE} bytes"
+        );
+        self.staged_sizes.insert(uploader.clone(), staged_size);
+        self.staged_code.insert(
+            code_hash,
+            StagedCode {
+                uploader,
+                code_size,
+                size: 0,
+                num_chunks: 0,
+                complete: false,
+                num_requests: 0,
+            },
+        );
+    }
+
+    fn remove_staged_code(&mut self, code_hash: CryptoHash) {
+        let staged = self.staged_code.remove(&code_hash).unwrap();
+        for index in 0..staged.num_chunks {
+            self.staged_chunks.remove(&(code_hash, index));
+        }
+        let staged_size = self.staged_sizes.get(&staged.uploader).copied().unwrap_or_default();
+        if staged_size > staged.code_size {
+            self.staged_sizes
+                .insert(staged.uploader, staged_size - staged.code_size);
+        } else {
+            self.staged_sizes.remove(&staged.uploader);
+        }
+    }
+
+    fn staged_bytes(&self, code_hash: CryptoHash) -> Vec<u8> {
+        let staged = self.staged_code.get(&code_hash).expect("No such staged code");
+        let mut code = Vec::with_capacity(usize::try_from(staged.size).unwrap_or_default());
+        for index in 0..staged.num_chunks {
+            code.extend_from_slice(self.staged_chunks.get(&(code_hash, index)).unwrap());
+        }
+        code
+    }
+}
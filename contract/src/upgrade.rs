@@ -0,0 +1,61 @@
+use multisig_model::{MultisigRequestId, RequestOutcome, UpgradeRecord};
+use near_sdk::{
+    env, is_promise_success, json_types::Base58CryptoHash, near_bindgen, Gas, GasWeight, NearToken, Promise,
+};
+
+use crate::{MultiSigContract, MultiSigContractExt};
+
+/// Method called on the newly deployed code in the same batch as the deployment.
+pub(crate) const MIGRATE_METHOD_NAME: &str = "migrate";
+
+/// Minimal gas for the migration. The rest of the unused gas is distributed to it as well.
+const MIGRATE_MIN_GAS: Gas = Gas::from_tgas(20);
+
+const ON_UPGRADE_SELF_GAS: Gas = Gas::from_tgas(10);
+
+// Deploys the code and calls `migrate` in a single batch, so a failed migration reverts the deployment
+pub(crate) fn upgrade_self(
+    promise: Promise,
+    request_id: MultisigRequestId,
+    code: Vec<u8>,
+    migrate_args: Vec<u8>,
+) -> Promise {
+    let code_hash = env::sha256_array(&code);
+    promise
+        .deploy_contract(code)
+        .function_call_weight(
+            MIGRATE_METHOD_NAME.to_string(),
+            migrate_args,
+            NearToken::from_yoctonear(0),
+            MIGRATE_MIN_GAS,
+            GasWeight(1),
+        )
+        .then(
+            MultiSigContract::ext(env::current_account_id())
+                .with_static_gas(ON_UPGRADE_SELF_GAS)
+                .on_upgrade_self(request_id, code_hash.into()),
+        )
+}
+
+#[near_bindgen]
+impl MultiSigContract {
+    /// Records the upgrade if the deployment and migration succeeded, releases or cancels requests depending on it.
+    /// Runs on the newly deployed code on success, so every version of the contract must keep this callback.
+    #[private]
+    pub fn on_upgrade_self(&mut self, request_id: MultisigRequestId, code_hash: Base58CryptoHash) {
+        if !is_promise_success() {
+            env::log_str(&format!("Upgrade in request {request_id} failed"));
+            self.finish_request(request_id, RequestOutcome::Failed);
+            return;
+        }
+        self.finish_request(request_id, RequestOutcome::Succeeded);
+
+        self.upgrades.push(UpgradeRecord {
+            request_id,
+            old_code_hash: self.code_hash,
+            new_code_hash: code_hash,
+            timestamp: env::block_timestamp(),
+        });
+        self.code_hash = Some(code_hash);
+    }
+}
@@ -0,0 +1,120 @@
+use multisig_model::{MultisigRequestId, Stream, StreamId};
+use near_sdk::{
+    env, is_promise_success,
+    json_types::{U128, U64},
+    near_bindgen,
+    serde_json::json,
+    Gas, NearToken, Promise,
+};
+
+use crate::{allowance::FT_TRANSFER_METHOD_NAME, event::MultisigEvent, MultiSigContract, MultiSigContractExt};
+
+const FT_TRANSFER_GAS: Gas = Gas::from_tgas(10);
+
+const ON_CLAIM_GAS: Gas = Gas::from_tgas(10);
+
+impl MultiSigContract {
+    // Stores the stream created by the request - used in execute_single_action
+    pub(crate) fn add_stream(&mut self, request_id: MultisigRequestId, stream: Stream) {
+        let stream_id = self.stream_nonce;
+        self.streams.insert(stream_id, stream);
+        self.stream_nonce += 1;
+        MultisigEvent::StreamCreated { stream_id, request_id }.emit();
+    }
+
+    // Transfers the vested and not yet claimed portion to the receiver - used in claim
+    pub(crate) fn claim_stream(&mut self, stream_id: StreamId) -> Promise {
+        assert!(self.paused.is_none(), "Multisig is paused");
+        let stream = self.streams.get_mut(&stream_id).expect("No such stream");
+        assert_eq!(
+            env::predecessor_account_id(),
+            stream.receiver_id,
+            "Only the receiver can claim the stream"
+        );
+        let amount = vested_amount(stream, env::block_timestamp()) - stream.claimed.0;
+        assert!(amount > 0, "Nothing to claim");
+        // claimed before the transfer, so it can't be claimed twice while the transfer is in flight
+        stream.claimed = U128(stream.claimed.0 + amount);
+        stream.pending_claims += 1;
+
+        let promise = match &stream.token_id {
+            None => Promise::new(stream.receiver_id.clone()).transfer(NearToken::from_yoctonear(amount)),
+            Some(token_id) => Promise::new(token_id.clone()).function_call(
+                FT_TRANSFER_METHOD_NAME.to_string(),
+                json!({ "receiver_id": stream.receiver_id, "amount": U128(amount) })
+                    .to_string()
+                    .into_bytes(),
+                NearToken::from_yoctonear(1),
+                FT_TRANSFER_GAS,
+            ),
+        };
+        promise.then(
+            MultiSigContract::ext(env::current_account_id())
+                .with_static_gas(ON_CLAIM_GAS)
+                .on_claim(stream_id, U128(amount)),
+        )
+    }
+
+    // Stops vesting at the current time, the vested portion stays claimable. The stream is removed right away when
+    // nothing is left to claim and no claim is in flight - used in RevokeStream
+    pub(crate) fn revoke_stream(&mut self, stream_id: StreamId) {
+        let stream = self.streams.get_mut(&stream_id).expect("No such stream");
+        let now = env::block_timestamp();
+        stream.total = U128(vested_amount(stream, now));
+        stream.end = U64(now.clamp(stream.start.0, stream.end.0));
+        stream.cliff = U64(stream.cliff.0.min(stream.end.0));
+        if stream.claimed == stream.total && stream.pending_claims == 0 {
+            self.streams.remove(&stream_id);
+        }
+    }
+
+    pub(crate) fn claimable_amount(&self, stream_id: StreamId) -> u128 {
+        let stream = self.streams.get(&stream_id).expect("No such stream");
+        vested_amount(stream, env::block_timestamp()) - stream.claimed.0
+    }
+}
+
+#[near_bindgen]
+impl MultiSigContract {
+    /// Restores the claimed amount if the transfer failed, removes the stream once it is fully claimed
+    /// and no other claim is in flight.
+    #[private]
+    pub fn on_claim(&mut self, stream_id: StreamId, amount: U128) {
+        let Some(stream) = self.streams.get_mut(&stream_id) else {
+            return;
+        };
+        stream.pending_claims = stream.pending_claims.saturating_sub(1);
+        if !is_promise_success() {
+            env::log_str(&format!("Claim of stream {stream_id} failed"));
+            stream.claimed = U128(stream.claimed.0 - amount.0);
+            return;
+        }
+        if stream.claimed == stream.total && stream.pending_claims == 0 {
+            self.streams.remove(&stream_id);
+        }
+    }
+}
+
+// Rejects streams that can never vest - used in add_request
+pub(crate) fn assert_valid_stream(start: u64, cliff: u64, end: u64, total: u128) {
+    assert!(start < end, "Stream must end after it starts");
+    assert!(
+        start <= cliff && cliff <= end,
+        "Cliff must be between start and end of the stream"
+    );
+    assert!(total > 0, "Stream total must be positive");
+}
+
+// Linear vesting from start to end, nothing before the cliff
+fn vested_amount(stream: &Stream, now: u64) -> u128 {
+    if now < stream.cliff.0 {
+        return 0;
+    }
+    if now >= stream.end.0 {
+        return stream.total.0;
+    }
+    let elapsed = u128::from(now - stream.start.0);
+    let duration = u128::from(stream.end.0 - stream.start.0);
+    // total * elapsed / duration without overflowing u128
+    stream.total.0 / duration * elapsed + stream.total.0 % duration * elapsed / duration
+}
@@ -0,0 +1,280 @@
+use multisig_model::Member;
+use near_sdk::{
+    env, is_promise_success,
+    json_types::{Base58CryptoHash, U64},
+    near, serde_json,
+    store::{IterableMap, LookupMap, Vector},
+    AccountId, BorshStorageKey, CryptoHash, Gas, NearToken, PanicOnDefault, Promise,
+};
+
+const NEW_WITH_CONFIG_GAS: Gas = Gas::from_tgas(30);
+const ON_MULTISIG_CREATED_GAS: Gas = Gas::from_tgas(10);
+
+#[near(serializers=[borsh])]
+#[derive(BorshStorageKey)]
+enum StorageKey {
+    Code,
+    CodeHashes,
+    Multisigs,
+}
+
+/// Options of a new multisig.
+#[near(serializers=[json])]
+#[derive(Default)]
+pub struct CreateConfig {
+    /// Version of the multisig code to deploy, the latest stored one if not set.
+    pub code_hash: Option<Base58CryptoHash>,
+    /// Options passed to `new_with_config` of the multisig, its defaults are used if not set.
+    pub elevated_num_confirmations: Option<usize>,
+    pub active_requests_limit: Option<u32>,
+    pub request_cooldown: Option<U64>,
+    pub request_expiry: Option<U64>,
+}
+
+/// A multisig deployed by the factory.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultisigInstance {
+    pub account_id: AccountId,
+    /// Hash of the code the multisig was created with, it might have been upgraded since.
+    pub code_hash: Base58CryptoHash,
+    pub created_at: u64,
+}
+
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
+pub struct MultisigFactory {
+    // multisig code by its sha256, stored once and deployed to every created multisig
+    code: LookupMap<CryptoHash, Vec<u8>>,
+    // in the order the code was stored, the last one is deployed by default
+    code_hashes: Vector<Base58CryptoHash>,
+    multisigs: IterableMap<AccountId, MultisigInstance>,
+}
+
+#[near]
+impl MultisigFactory {
+    #[init]
+    pub fn new() -> Self {
+        assert!(!env::state_exists(), "Already initialized");
+        Self {
+            code: LookupMap::new(StorageKey::Code),
+            code_hashes: Vector::new(StorageKey::CodeHashes),
+            multisigs: IterableMap::new(StorageKey::Multisigs),
+        }
+    }
+
+    /// Store a new version of the multisig code passed as raw input. New multisigs are created with it by default.
+    #[private]
+    pub fn store_code(&mut self) -> Base58CryptoHash {
+        let code = env::input().expect("Expected multisig code as input");
+        let code_hash = env::sha256_array(&code);
+        assert!(!self.code.contains_key(&code_hash), "Code is already stored");
+        self.code.insert(code_hash, code);
+        self.code_hashes.push(code_hash.into());
+        code_hash.into()
+    }
+
+    /// Create `name` sub-account of the factory with the multisig deployed and initialized with `members`
+    /// and `threshold`. The multisig adds a function call access key for each of the key members.
+    /// The attached deposit is transferred to the multisig and must cover its storage, it is refunded on failure.
+    #[payable]
+    pub fn create_multisig(
+        &mut self,
+        name: String,
+        members: Vec<Member>,
+        threshold: usize,
+        config: Option<CreateConfig>,
+    ) -> Promise {
+        let config = config.unwrap_or_default();
+        assert!(!name.contains('.'), "Multisig name must not contain dots");
+        let account_id: AccountId = format!("{name}.{}", env::current_account_id())
+            .parse()
+            .expect("Invalid multisig name");
+        assert!(!self.multisigs.contains_key(&account_id), "Multisig already exists");
+        assert!(
+            threshold > 0 && threshold <= members.len(),
+            "Threshold must be between 1 and the number of members"
+        );
+        let code_hash: CryptoHash = config
+            .code_hash
+            .or_else(|| self.code_hashes.iter().last().copied())
+            .expect("No multisig code stored")
+            .into();
+        let code = self.code.get(&code_hash).expect("No such code version").clone();
+
+        let args = serde_json::json!({
+            "members": members,
+            "num_confirmations": threshold,
+            "elevated_num_confirmations": config.elevated_num_confirmations,
+            "active_requests_limit": config.active_requests_limit,
+            "request_cooldown": config.request_cooldown,
+            "request_expiry": config.request_expiry,
+        });
+        let promise = Promise::new(account_id.clone())
+            .create_account()
+            .transfer(env::attached_deposit())
+            .deploy_contract(code)
+            .function_call(
+                "new_with_config".to_string(),
+                args.to_string().into_bytes(),
+                NearToken::from_yoctonear(0),
+                NEW_WITH_CONFIG_GAS,
+            );
+
+        // registered right away so the same name can't be taken twice while the creation is in progress
+        self.multisigs.insert(
+            account_id.clone(),
+            MultisigInstance {
+                account_id: account_id.clone(),
+                code_hash: code_hash.into(),
+                created_at: env::block_timestamp(),
+            },
+        );
+        promise.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(ON_MULTISIG_CREATED_GAS)
+                .on_multisig_created(account_id, env::predecessor_account_id(), env::attached_deposit()),
+        )
+    }
+
+    #[private]
+    pub fn on_multisig_created(&mut self, account_id: AccountId, creator_id: AccountId, deposit: NearToken) -> bool {
+        if is_promise_success() {
+            return true;
+        }
+        // the deposit is returned to the factory when the account can't be created
+        self.multisigs.remove(&account_id);
+        if !deposit.is_zero() {
+            Promise::new(creator_id).transfer(deposit).detach();
+        }
+        false
+    }
+
+    /// Stored versions of the multisig code, the last one is deployed by default.
+    pub fn list_code_versions(&self) -> Vec<Base58CryptoHash> {
+        self.code_hashes.iter().copied().collect()
+    }
+
+    /// Multisigs created by the factory with the code they were deployed with.
+    pub fn list_multisigs(&self, from_index: u32, limit: u32) -> Vec<MultisigInstance> {
+        self.multisigs
+            .values()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get_multisig(&self, account_id: AccountId) -> Option<MultisigInstance> {
+        self.multisigs.get(&account_id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use near_sdk::{
+        test_utils::{
+            test_env::{alice, bob},
+            VMContextBuilder,
+        },
+        testing_env, AccountId, NearToken, PromiseResult, PublicKey, VMContext,
+    };
+
+    use super::*;
+
+    fn factory() -> AccountId {
+        AccountId::from_str("factory.near").unwrap()
+    }
+
+    fn context(predecessor: AccountId) -> VMContext {
+        VMContextBuilder::new()
+            .current_account_id(factory())
+            .predecessor_account_id(predecessor)
+            .attached_deposit(NearToken::from_near(5))
+            .build()
+    }
+
+    fn members() -> Vec<Member> {
+        vec![
+            Member::Key(PublicKey::from_str("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy").unwrap()),
+            Member::Account(bob()),
+        ]
+    }
+
+    fn factory_with_code(code: &[u8]) -> MultisigFactory {
+        let mut context = context(factory());
+        context.input = code.into();
+        testing_env!(context);
+        let mut contract = MultisigFactory::new();
+        contract.store_code();
+        contract
+    }
+
+    #[test]
+    fn test_create_multisig() {
+        let mut contract = factory_with_code(b"v1");
+        let mut context_v2 = context(factory());
+        context_v2.input = b"v2".as_slice().into();
+        testing_env!(context_v2);
+        let v2 = contract.store_code();
+        let v1 = contract.list_code_versions()[0];
+        assert_eq!(contract.list_code_versions(), vec![v1, v2]);
+
+        testing_env!(context(alice()));
+        contract.create_multisig("team".to_string(), members(), 2, None);
+        let team = contract
+            .get_multisig(AccountId::from_str("team.factory.near").unwrap())
+            .unwrap();
+        assert_eq!(team.code_hash, v2);
+
+        let config = CreateConfig {
+            code_hash: Some(v1),
+            active_requests_limit: Some(5),
+            ..CreateConfig::default()
+        };
+        contract.create_multisig("legacy".to_string(), members(), 1, Some(config));
+        let legacy = contract
+            .get_multisig(AccountId::from_str("legacy.factory.near").unwrap())
+            .unwrap();
+        assert_eq!(legacy.code_hash, v1);
+        assert_eq!(contract.list_multisigs(0, 10), vec![team, legacy]);
+    }
+
+    #[test]
+    fn test_multisig_removed_on_failure() {
+        let mut contract = factory_with_code(b"v1");
+        testing_env!(context(alice()));
+        contract.create_multisig("team".to_string(), members(), 2, None);
+
+        testing_env!(
+            context(factory()),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        let account_id = AccountId::from_str("team.factory.near").unwrap();
+        assert!(!contract.on_multisig_created(account_id.clone(), alice(), NearToken::from_near(5)));
+        assert!(contract.get_multisig(account_id).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Multisig already exists")]
+    fn test_panics_create_existing_multisig() {
+        let mut contract = factory_with_code(b"v1");
+        testing_env!(context(alice()));
+        contract.create_multisig("team".to_string(), members(), 2, None);
+        testing_env!(context(bob()));
+        contract.create_multisig("team".to_string(), members(), 1, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Threshold must be between 1 and the number of members")]
+    fn test_panics_threshold_above_members() {
+        let mut contract = factory_with_code(b"v1");
+        testing_env!(context(alice()));
+        contract.create_multisig("team".to_string(), members(), 3, None);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod context;
 mod happy_flow;
+mod measure;
@@ -0,0 +1,83 @@
+#![cfg(test)]
+
+use std::{fs::OpenOptions, io::Write};
+
+use anyhow::Result;
+use near_workspaces::{result::ExecutionFinalResult, Contract};
+use serde_json::json;
+
+use crate::context::{Context, MULTISIG};
+
+/// Multisig built from the code that kept its state in `near_sdk::collections`.
+const MULTISIG_BASELINE: &str = "multisig_baseline";
+
+const MEASURED_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../measured.txt");
+
+fn record(version: &str, method: &str, result: &ExecutionFinalResult) -> Result<()> {
+    let gas = result.total_gas_burnt;
+    println!("⛽️ {version} {method}: {gas}");
+
+    let mut file = OpenOptions::new().create(true).append(true).open(MEASURED_FILE)?;
+    writeln!(file, "{version} {method}: {gas}")?;
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn measure_request_lifecycle_test() -> Result<()> {
+    println!("👷🏽 Measure gas of request lifecycle");
+
+    let context = Context::new(&[MULTISIG_BASELINE, MULTISIG], true, "build-integration".into()).await?;
+
+    for (version, name) in [("before", MULTISIG_BASELINE), ("after", MULTISIG)] {
+        let multisig = context.contracts[name].clone();
+        multisig
+            .call("new")
+            .args_json(json!({ "num_confirmations": 2 }))
+            .transact()
+            .await?
+            .into_result()?;
+        measure_request_lifecycle(&context, &multisig, version).await?;
+    }
+
+    Ok(())
+}
+
+async fn measure_request_lifecycle(context: &Context, multisig: &Contract, version: &str) -> Result<()> {
+    let result = multisig
+        .call("add_request")
+        .args_json(json!({
+            "request": {
+                "receiver_id": multisig.id(),
+                "actions": [{ "type": "Transfer", "amount": "1" }],
+            }
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    record(version, "add_request", &result)?;
+    let request_id: u32 = result.json()?;
+
+    let result = multisig
+        .call("confirm")
+        .args_json(json!({ "request_id": request_id }))
+        .max_gas()
+        .transact()
+        .await?;
+    record(version, "confirm", &result)?;
+    result.into_result()?;
+
+    context.fast_forward_hours(1).await?;
+
+    let result = multisig
+        .call("delete_request")
+        .args_json(json!({ "request_id": request_id }))
+        .max_gas()
+        .transact()
+        .await?;
+    record(version, "delete_request", &result)?;
+    result.into_result()?;
+
+    Ok(())
+}
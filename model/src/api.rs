@@ -1,9 +1,33 @@
-use near_sdk::PublicKey;
+use near_sdk::{
+    json_types::{Base58CryptoHash, Base64VecU8, U128, U64},
+    AccountId, Gas, PublicKey,
+};
 use nitka::make_integration_version;
 #[cfg(feature = "integration-api")]
 use nitka::near_sdk;
 
-use crate::data::{MultiSigRequest, MultisigRequestId};
+use crate::{
+    data::{
+        DeleteOutcome, ExecutionResult, FailedRequest, Member, MultiSigRequest, MultiSigRequestActionKind,
+        MultisigConfig, MultisigRequestId, PauseInfo, RecoveryConfig, RecoveryProposal, RequestRecord, RetryConfig,
+        ScheduleId, ScheduledRequest, SpendingLimit, StagedCodeInfo, Stream, StreamId, UpgradeRecord,
+    },
+    policy::Policy,
+};
+
+/// Methods of the multisig that signer keys are allowed to call.
+pub const SIGNER_METHODS: &[&str] = &[
+    "add_request",
+    "add_request_and_confirm",
+    "delete_request",
+    "confirm",
+    "stage_code",
+    "unstage_code",
+    "pause",
+    "cancel_recovery",
+    "retry",
+    "execute_ready",
+];
 
 #[cfg(feature = "integration-api")]
 pub struct MultisigContract<'a> {
@@ -16,6 +40,22 @@ pub trait MultisigApi {
     /// @params num_confirmations: k of n signatures required to perform operations.
     fn new(num_confirmations: usize) -> Self;
 
+    /// Initialize multisig contract with its members and configuration.
+    /// Access keys are added for key members, account members confirm by calling the multisig directly.
+    /// @params num_confirmations: k of n members required to perform operations.
+    /// @params elevated_num_confirmations: members required for security-critical requests, `num_confirmations` if not set.
+    /// @params active_requests_limit: pending requests per member, 12 if not set.
+    /// @params request_cooldown: time in nanoseconds before a request can be deleted, 15 minutes if not set.
+    /// @params request_expiry: time in nanoseconds after which a request can't be confirmed, never if not set.
+    fn new_with_config(
+        members: Vec<Member>,
+        num_confirmations: usize,
+        elevated_num_confirmations: Option<usize>,
+        active_requests_limit: Option<u32>,
+        request_cooldown: Option<U64>,
+        request_expiry: Option<U64>,
+    ) -> Self;
+
     /// Add request for multisig.
     fn add_request(&mut self, request: MultiSigRequest) -> MultisigRequestId;
 
@@ -23,11 +63,50 @@ pub trait MultisigApi {
     fn add_request_and_confirm(&mut self, request: MultiSigRequest) -> MultisigRequestId;
 
     /// Remove given request and associated confirmations.
-    fn delete_request(&mut self, request_id: MultisigRequestId) -> MultiSigRequest;
+    /// The member that added the request can remove it at any time. Other members can vote to remove it once
+    /// the cooldown is over, and it is removed when `num_confirmations` members voted.
+    /// Failed requests are removed the same way, their cooldown starts when they failed.
+    fn delete_request(&mut self, request_id: MultisigRequestId) -> DeleteOutcome;
 
-    /// Confirm given request with given signing key.
+    /// Confirm given request with given signing key, or as the member account calling the multisig.
     /// If with this, there has been enough confirmation, a promise with request will be scheduled.
     fn confirm(&mut self, request_id: MultisigRequestId) -> ::near_sdk::PromiseOrValue<()>;
+
+    /// Append a chunk of code to be deployed later with `DeployStagedCode`. `code_size` is the size of the whole code,
+    /// the same for every chunk, the hash is checked once the uploaded chunks reach it.
+    /// Returns the size of the code uploaded so far, `get_staged_code` shows whether the upload is complete.
+    fn stage_code(&mut self, code_hash: Base58CryptoHash, code_size: U64, chunk: Base64VecU8) -> U64;
+
+    /// Remove staged code no request deploys, e.g. an abandoned upload.
+    /// Only the uploader can remove it, unless they are no longer a member.
+    fn unstage_code(&mut self, code_hash: Base58CryptoHash);
+
+    /// Pause the multisig with a guardian key. Only an `Unpause` request can be added, confirmed and executed
+    /// until the multisig is unpaused.
+    fn pause(&mut self);
+
+    /// Propose new signers and threshold, called by a recovery account.
+    /// The proposal can be executed after the recovery delay unless a member cancels it.
+    fn propose_recovery(&mut self, keys: Vec<PublicKey>, removed_keys: Vec<PublicKey>, num_confirmations: usize);
+
+    /// Cancel the pending recovery proposal with a single member key or account.
+    fn cancel_recovery(&mut self);
+
+    /// Replace the signers with the pending recovery proposal once its delay is over.
+    fn execute_recovery(&mut self) -> ::near_sdk::PromiseOrValue<()>;
+
+    /// Execute the next due run of the schedule. Can be called by anyone, e.g. a keeper bot.
+    fn trigger_scheduled(&mut self, schedule_id: ScheduleId) -> ::near_sdk::PromiseOrValue<()>;
+
+    /// Withdraw the vested and not yet claimed portion of the stream, called by its receiver.
+    fn claim(&mut self, stream_id: StreamId) -> ::near_sdk::PromiseOrValue<()>;
+
+    /// Execute the failed request again with the confirmations it has, optionally raising the gas of its function calls.
+    fn retry(&mut self, request_id: MultisigRequestId, gas: Option<Gas>) -> ::near_sdk::PromiseOrValue<()>;
+
+    /// Execute the pending request if the confirmations it has meet the current threshold,
+    /// e.g. after the threshold was lowered.
+    fn execute_ready(&mut self, request_id: MultisigRequestId) -> ::near_sdk::PromiseOrValue<()>;
 }
 
 #[make_integration_version]
@@ -36,11 +115,98 @@ pub trait MultisigView {
 
     fn get_num_requests_pk(&self, public_key: PublicKey) -> u32;
 
+    /// Ids of pending requests added by the given key or member account.
+    fn list_requests_by_signer(&self, signer: Member) -> Vec<MultisigRequestId>;
+
     fn list_request_ids(&self) -> Vec<MultisigRequestId>;
 
-    fn get_confirmations(&self, request_id: MultisigRequestId) -> Vec<PublicKey>;
+    /// Ids of pending requests that can be executed with `execute_ready`.
+    fn list_ready_requests(&self) -> Vec<MultisigRequestId>;
+
+    fn get_confirmations(&self, request_id: MultisigRequestId) -> Vec<Member>;
+
+    /// Members that voted to delete the pending request.
+    fn get_delete_votes(&self, request_id: MultisigRequestId) -> Vec<Member>;
+
+    /// Dependencies of the pending request that are not executed yet.
+    fn get_dependencies(&self, request_id: MultisigRequestId) -> Vec<MultisigRequestId>;
+
+    /// Confirmations of the request made by keys that were removed with `DeleteKey` since.
+    fn get_revoked_confirmations(&self, request_id: MultisigRequestId) -> Vec<PublicKey>;
 
     fn get_num_confirmations(&self) -> usize;
 
+    fn get_config(&self) -> MultisigConfig;
+
+    /// Time in nanoseconds before a request containing actions of the kind can be deleted, the default one if `None`.
+    fn get_request_cooldown(&self, kind: Option<MultiSigRequestActionKind>) -> U64;
+
     fn get_request_nonce(&self) -> u32;
+
+    /// Number of confirmations required for security-critical requests, such as `UpgradeSelf`.
+    fn get_elevated_num_confirmations(&self) -> usize;
+
+    /// Hash of the code deployed by the last `UpgradeSelf` request.
+    fn get_code_hash(&self) -> Option<Base58CryptoHash>;
+
+    fn list_upgrades(&self) -> Vec<UpgradeRecord>;
+
+    /// Outcomes of executed requests in the order they were resolved.
+    fn list_history(&self, from_index: u32, limit: u32) -> Vec<RequestRecord>;
+
+    fn list_failed_request_ids(&self) -> Vec<MultisigRequestId>;
+
+    fn get_failed_request(&self, request_id: MultisigRequestId) -> Option<FailedRequest>;
+
+    fn get_retry_config(&self) -> RetryConfig;
+
+    /// Value returned by the last action of the executed request, e.g. the id of a created DAO proposal.
+    fn get_execution_result(&self, request_id: MultisigRequestId) -> Option<ExecutionResult>;
+
+    fn get_staged_code(&self, code_hash: Base58CryptoHash) -> Option<StagedCodeInfo>;
+
+    /// Spending limit of the token, `None` for NEAR.
+    fn get_spending_limit(&self, token_id: Option<AccountId>) -> Option<SpendingLimit>;
+
+    /// Amount of the token (`None` for NEAR) that can be spent with a single confirmation in the current period.
+    /// `member` is required if the limit is per member.
+    fn get_remaining_allowance(&self, token_id: Option<AccountId>, member: Option<Member>) -> U128;
+
+    /// Effective restrictions on requests the multisig can execute.
+    fn get_policy(&self) -> Policy;
+
+    fn list_guardians(&self) -> Vec<PublicKey>;
+
+    /// Accounts that confirm by calling the multisig directly, in addition to its access keys.
+    fn list_members(&self) -> Vec<AccountId>;
+
+    /// Keys known to be signers: added or registered by the multisig, or used to add or confirm a request.
+    fn list_signer_keys(&self) -> Vec<PublicKey>;
+
+    /// Whether `list_signer_keys` lists every signer key, which is required to add a full access key.
+    /// False for multisigs created with `new` or migrated until their keys are registered with `RegisterSignerKeys`.
+    fn are_signer_keys_complete(&self) -> bool;
+
+    /// Who paused the multisig and when, `None` if it is not paused.
+    fn get_pause_info(&self) -> Option<PauseInfo>;
+
+    fn get_recovery_config(&self) -> Option<RecoveryConfig>;
+
+    /// Pending recovery proposal, `None` if there is none.
+    fn get_recovery_proposal(&self) -> Option<RecoveryProposal>;
+
+    /// Time the member last added, confirmed or deleted a request or cancelled a recovery proposal,
+    /// or was added as a signer key. `None` if it is unknown.
+    fn get_last_activity(&self, member: Member) -> Option<u64>;
+
+    fn list_schedule_ids(&self) -> Vec<ScheduleId>;
+
+    fn get_schedule(&self, schedule_id: ScheduleId) -> Option<ScheduledRequest>;
+
+    fn list_stream_ids(&self) -> Vec<StreamId>;
+
+    fn get_stream(&self, stream_id: StreamId) -> Option<Stream>;
+
+    /// Amount of the stream the receiver can claim now.
+    fn get_claimable(&self, stream_id: StreamId) -> U128;
 }
@@ -1,9 +1,17 @@
+use std::collections::BTreeMap;
+
 use near_sdk::{
-    json_types::{Base64VecU8, U128},
-    near, AccountId, Gas, NearToken, PublicKey,
+    json_types::{Base58CryptoHash, Base64VecU8, U128, U64},
+    near,
+    serde::{Deserialize, Deserializer},
+    serde_json, AccountId, Gas, NearToken, PublicKey,
 };
 
+use crate::policy::Policy;
+
 pub type MultisigRequestId = u32;
+pub type ScheduleId = u32;
+pub type StreamId = u32;
 
 /// Permissions for function call access key.
 
@@ -15,6 +23,15 @@ pub struct FunctionCallPermission {
     pub method_names: Vec<String>,
 }
 
+/// Access key of a signer and the permission it was added with, see `RegisterSignerKeys`.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignerKey {
+    pub public_key: PublicKey,
+    /// Must be restricted to the multisig itself.
+    pub permission: FunctionCallPermission,
+}
+
 /// Lowest level action that can be performed by the multisig contract.
 #[near(serializers=[borsh, json])]
 #[derive(Debug, Clone, PartialEq)]
@@ -25,8 +42,11 @@ pub enum MultiSigRequestAction {
     /// Create a new account.
     CreateAccount,
     /// Deploys contract to receiver's account. Can upgrade given contract as well.
+    /// Requires `elevated_num_confirmations` confirmations if the receiver is the multisig itself.
     DeployContract { code: Base64VecU8 },
     /// Adds key, either new key for multisig or full access key to another account.
+    /// A full access key to the multisig itself requires confirmations of all members, see `AddSignerKey` for signers,
+    /// and can't be added until all signer keys are known, see `RegisterSignerKeys`.
     AddKey {
         public_key: PublicKey,
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -42,13 +62,209 @@ pub enum MultiSigRequestAction {
         gas: Gas,
     },
     /// Sets number of confirmations required to authorize requests.
+    /// Kept for compatibility, same as `UpdateConfig` changing only this setting.
     /// Can not be bundled with any other actions or transactions.
     SetNumConfirmations { num_confirmations: usize },
     /// Sets number of active requests (unconfirmed requests) per access key
     /// Default is 12 unconfirmed requests at a time
-    /// The REQUEST_COOLDOWN for requests is 15min
-    /// Worst gas attack a malicious keyholder could do is 12 requests every 15min
+    /// The request cooldown is 15min by default, see `SetRequestCooldown`
+    /// Worst gas attack a malicious keyholder could do is 12 requests every cooldown
+    /// Kept for compatibility, same as `UpdateConfig` changing only this setting.
     SetActiveRequestsLimit { active_requests_limit: u32 },
+    /// Deploys new code to the multisig itself and calls `migrate` with `migrate_args` in the same batch,
+    /// so the deployment is reverted if the migration fails.
+    /// Can not be bundled with any other actions or transactions.
+    /// Requires `elevated_num_confirmations` confirmations.
+    UpgradeSelf {
+        code: Base64VecU8,
+        migrate_args: Base64VecU8,
+    },
+    /// Sets number of confirmations required to authorize security-critical requests, such as `UpgradeSelf`.
+    /// The effective value is never lower than `num_confirmations`.
+    /// Requires `elevated_num_confirmations` confirmations.
+    /// Kept for compatibility, same as `UpdateConfig` changing only this setting.
+    /// Can not be bundled with any other actions or transactions.
+    SetElevatedNumConfirmations { num_confirmations: usize },
+    /// Deploys code previously uploaded with `stage_code` to receiver's account.
+    /// The staged code is freed once deployed or when the request is removed, a failed request keeps it to be retried.
+    /// Requires `elevated_num_confirmations` confirmations if the receiver is the multisig itself.
+    DeployStagedCode { code_hash: Base58CryptoHash },
+    /// Sets the limit for `Transfer` (if `token_id` is `None`) or `ft_transfer` requests of the given token
+    /// that can be executed with a single confirmation. `None` removes the limit.
+    /// Kept for compatibility, same as `UpdateConfig` changing only this setting.
+    /// Can not be bundled with any other actions or transactions.
+    SetSpendingLimit {
+        token_id: Option<AccountId>,
+        limit: Option<SpendingLimit>,
+    },
+    /// Replaces the policy restricting what requests the multisig can execute.
+    /// Kept for compatibility, same as `UpdateConfig` changing only this setting.
+    /// Can not be bundled with any other actions or transactions.
+    /// Requires `elevated_num_confirmations` confirmations.
+    SetPolicy { policy: Policy },
+    /// Replaces the set of guardian keys that can pause the multisig alone.
+    /// Kept for compatibility, same as `UpdateConfig` changing only this setting.
+    /// Can not be bundled with any other actions or transactions.
+    SetGuardians { guardians: Vec<PublicKey> },
+    /// Resumes the multisig paused by a guardian. The only request that can be added, confirmed and executed while paused.
+    /// Replaces the guardian keys if `guardians` is set, e.g. to drop a leaked guardian key that would pause it again.
+    /// Can not be bundled with any other actions or transactions.
+    /// Always requires `num_confirmations` confirmations.
+    Unpause { guardians: Option<Vec<PublicKey>> },
+    /// Sets accounts that can propose a new set of signers, e.g. when too many keys are lost. `None` disables recovery.
+    /// Drops the pending recovery proposal.
+    /// Kept for compatibility, same as `UpdateConfig` changing only this setting.
+    /// Can not be bundled with any other actions or transactions.
+    /// Requires `elevated_num_confirmations` confirmations.
+    SetRecovery { recovery: Option<RecoveryConfig> },
+    /// Stores the request to be executed `count` times every `interval` nanoseconds starting at `start`,
+    /// each run is triggered with `trigger_scheduled`. Only `Transfer` and `FunctionCall` actions can be scheduled.
+    /// `interval` must be positive unless there is a single run.
+    /// Can not be bundled with any other actions or transactions.
+    Schedule {
+        request: MultiSigRequest,
+        start: U64,
+        interval: U64,
+        count: u32,
+    },
+    /// Removes the schedule, its remaining runs are not executed.
+    /// Can not be bundled with any other actions or transactions.
+    CancelSchedule { schedule_id: ScheduleId },
+    /// Vests `total` of the token (`None` for NEAR) to `receiver_id` linearly from `start` to `end`,
+    /// nothing can be claimed before `cliff`. All times are timestamps in nanoseconds.
+    /// Can not be bundled with any other actions or transactions.
+    CreateStream {
+        token_id: Option<AccountId>,
+        receiver_id: AccountId,
+        start: U64,
+        cliff: U64,
+        end: U64,
+        total: U128,
+    },
+    /// Stops vesting of the stream. The portion vested so far can still be claimed, the rest stays in the multisig.
+    /// Can not be bundled with any other actions or transactions.
+    RevokeStream { stream_id: StreamId },
+    /// Executes transactions to many receivers at once, e.g. paying contributors. Only `Transfer` and `FunctionCall`
+    /// actions can be batched. Success of each transaction is recorded in the request history.
+    /// Can not be bundled with any other actions or transactions.
+    Batch { transactions: Vec<MultiSigRequest> },
+    /// Sets how many times and for how long failed requests can be retried.
+    /// Kept for compatibility, same as `UpdateConfig` changing only this setting.
+    /// Can not be bundled with any other actions or transactions.
+    SetRetryConfig { retry_config: RetryConfig },
+    /// Adds an account that confirms by calling the multisig from its own account, e.g. another multisig or a DAO.
+    /// Can not be bundled with any other actions or transactions.
+    AddMember { account_id: AccountId },
+    /// Removes the member account, deleting its pending requests and confirmations like `DeleteKey` does for keys.
+    /// Can not be bundled with any other actions or transactions.
+    RemoveMember { account_id: AccountId },
+    /// Sets the time in nanoseconds before a request can be deleted, and replaces the overrides for requests
+    /// containing actions of the given kinds. A request can be deleted after the longest cooldown of its actions.
+    /// Kept for compatibility, same as `UpdateConfig` changing only these settings.
+    /// Can not be bundled with any other actions or transactions.
+    SetRequestCooldown {
+        cooldown: U64,
+        #[serde(default)]
+        overrides: BTreeMap<MultiSigRequestActionKind, U64>,
+    },
+    /// Changes any settings of the multisig at once. The resulting configuration is validated as a whole,
+    /// either every change is applied or none.
+    /// Requires `elevated_num_confirmations` confirmations if it replaces the policy, the elevated threshold
+    /// or the recovery.
+    /// Can not be bundled with any other actions or transactions.
+    UpdateConfig { update: Box<ConfigUpdate> },
+    /// Replaces the signer key `old` with `new` in one request: the new key gets the same permission and the old one
+    /// is deleted. Pending requests added by the old key and its confirmations, delete votes and guardian rights move
+    /// to the new key. Only keys whose permission the multisig knows can be rotated: keys it added itself
+    /// or registered with `RegisterSignerKeys`. The new key can't be a signer key already or a key deleted from the multisig,
+    /// a deleted key is added back with `AddKey` or `AddSignerKey`.
+    RotateKey { old: PublicKey, new: PublicKey },
+    /// Adds a key of a new signer: a function call key that can only call the signer methods of the multisig,
+    /// e.g. `add_request` and `confirm`. `allowance` limits the gas fees it can spend, unlimited if not set.
+    AddSignerKey {
+        public_key: PublicKey,
+        allowance: Option<U128>,
+    },
+    /// Registers signer keys added outside of the multisig, e.g. before `new` or before the migration,
+    /// with the permission they were added with, so they can be rotated with `RotateKey`.
+    /// The registered keys together with `list_signer_keys` must be all signer keys of the multisig:
+    /// a full access `AddKey` is rejected until they are registered, and then requires all of them.
+    /// Can not be bundled with any other actions or transactions.
+    /// Requires `elevated_num_confirmations` confirmations.
+    RegisterSignerKeys { keys: Vec<SignerKey> },
+}
+
+/// Kind of `MultiSigRequestAction` regardless of its parameters.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MultiSigRequestActionKind {
+    Transfer,
+    CreateAccount,
+    DeployContract,
+    AddKey,
+    DeleteKey,
+    FunctionCall,
+    SetNumConfirmations,
+    SetActiveRequestsLimit,
+    UpgradeSelf,
+    SetElevatedNumConfirmations,
+    DeployStagedCode,
+    SetSpendingLimit,
+    SetPolicy,
+    SetGuardians,
+    Unpause,
+    SetRecovery,
+    Schedule,
+    CancelSchedule,
+    CreateStream,
+    RevokeStream,
+    Batch,
+    SetRetryConfig,
+    AddMember,
+    RemoveMember,
+    SetRequestCooldown,
+    UpdateConfig,
+    RotateKey,
+    AddSignerKey,
+    /// Changing the confirmations policy with `UpdateConfig`, there is no single-setting action for it.
+    SetConfirmationsPolicy,
+    RegisterSignerKeys,
+}
+
+impl MultiSigRequestAction {
+    pub fn kind(&self) -> MultiSigRequestActionKind {
+        match self {
+            Self::Transfer { .. } => MultiSigRequestActionKind::Transfer,
+            Self::CreateAccount => MultiSigRequestActionKind::CreateAccount,
+            Self::DeployContract { .. } => MultiSigRequestActionKind::DeployContract,
+            Self::AddKey { .. } => MultiSigRequestActionKind::AddKey,
+            Self::DeleteKey { .. } => MultiSigRequestActionKind::DeleteKey,
+            Self::FunctionCall { .. } => MultiSigRequestActionKind::FunctionCall,
+            Self::SetNumConfirmations { .. } => MultiSigRequestActionKind::SetNumConfirmations,
+            Self::SetActiveRequestsLimit { .. } => MultiSigRequestActionKind::SetActiveRequestsLimit,
+            Self::UpgradeSelf { .. } => MultiSigRequestActionKind::UpgradeSelf,
+            Self::SetElevatedNumConfirmations { .. } => MultiSigRequestActionKind::SetElevatedNumConfirmations,
+            Self::DeployStagedCode { .. } => MultiSigRequestActionKind::DeployStagedCode,
+            Self::SetSpendingLimit { .. } => MultiSigRequestActionKind::SetSpendingLimit,
+            Self::SetPolicy { .. } => MultiSigRequestActionKind::SetPolicy,
+            Self::SetGuardians { .. } => MultiSigRequestActionKind::SetGuardians,
+            Self::Unpause { .. } => MultiSigRequestActionKind::Unpause,
+            Self::SetRecovery { .. } => MultiSigRequestActionKind::SetRecovery,
+            Self::Schedule { .. } => MultiSigRequestActionKind::Schedule,
+            Self::CancelSchedule { .. } => MultiSigRequestActionKind::CancelSchedule,
+            Self::CreateStream { .. } => MultiSigRequestActionKind::CreateStream,
+            Self::RevokeStream { .. } => MultiSigRequestActionKind::RevokeStream,
+            Self::Batch { .. } => MultiSigRequestActionKind::Batch,
+            Self::SetRetryConfig { .. } => MultiSigRequestActionKind::SetRetryConfig,
+            Self::AddMember { .. } => MultiSigRequestActionKind::AddMember,
+            Self::RemoveMember { .. } => MultiSigRequestActionKind::RemoveMember,
+            Self::SetRequestCooldown { .. } => MultiSigRequestActionKind::SetRequestCooldown,
+            Self::UpdateConfig { .. } => MultiSigRequestActionKind::UpdateConfig,
+            Self::RotateKey { .. } => MultiSigRequestActionKind::RotateKey,
+            Self::AddSignerKey { .. } => MultiSigRequestActionKind::AddSignerKey,
+            Self::RegisterSignerKeys { .. } => MultiSigRequestActionKind::RegisterSignerKeys,
+        }
+    }
 }
 
 // The request the user makes specifying the receiving account and actions they want to execute (1 tx)
@@ -57,13 +273,343 @@ pub enum MultiSigRequestAction {
 pub struct MultiSigRequest {
     pub receiver_id: AccountId,
     pub actions: Vec<MultiSigRequestAction>,
+    /// Pending requests that must be executed successfully before this one.
+    /// Kept separately from the stored request, see `get_dependencies`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[borsh(skip)]
+    pub depends_on: Vec<MultisigRequestId>,
+}
+
+/// Member of the multisig: an access key of the multisig account or an account calling the multisig directly.
+/// Serialized to JSON as a plain public key or account id.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(untagged)]
+pub enum Member {
+    Key(PublicKey),
+    Account(AccountId),
 }
 
-// An internal request wrapped with the signer_pk and added timestamp to determine num_requests_pk and prevent against malicious key holder gas attacks
+// An internal request wrapped with the signer and added timestamp to determine num_requests_pk and prevent against malicious key holder gas attacks
 #[near(serializers=[borsh, json])]
 #[derive(Clone)]
 pub struct MultiSigRequestWithSigner {
     pub request: MultiSigRequest,
-    pub signer_pk: PublicKey,
+    pub signer: Member,
     pub added_timestamp: u64,
 }
+
+// A record of a successful `UpgradeSelf` request, kept to audit the code deployed to the multisig
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpgradeRecord {
+    pub request_id: MultisigRequestId,
+    /// Hash of the code replaced by the upgrade. `None` if the code was deployed outside of `UpgradeSelf`.
+    pub old_code_hash: Option<Base58CryptoHash>,
+    pub new_code_hash: Base58CryptoHash,
+    pub timestamp: u64,
+}
+
+// State of the code uploaded with `stage_code`
+#[near(serializers=[json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StagedCodeInfo {
+    /// Size of the code declared with the first chunk.
+    pub code_size: u64,
+    /// Size of the chunks uploaded so far.
+    pub size: u64,
+    /// Whether all chunks are uploaded and the sha256 of the uploaded bytes matches the code hash.
+    pub complete: bool,
+}
+
+/// Amount of NEAR or NEP-141 tokens that can be spent per period with a single confirmation.
+/// Anything above the limit requires the normal number of confirmations.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpendingLimit {
+    /// Amount in the smallest units of the token.
+    pub amount: U128,
+    /// Length of the period in nanoseconds. The spent amount is reset once the period is over.
+    pub period: U64,
+    /// Whether each member has its own allowance or all members share a single one.
+    pub per_member: bool,
+}
+
+// Who paused the multisig and when
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PauseInfo {
+    pub guardian: PublicKey,
+    pub timestamp: u64,
+}
+
+/// Accounts that can replace the signers of the multisig after a delay.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveryConfig {
+    pub accounts: Vec<AccountId>,
+    /// Time in nanoseconds any member has to cancel a proposal before it can be executed, at least 1 day.
+    pub delay: U64,
+    /// Time in nanoseconds the keys removed by a proposal must have been inactive before it is accepted.
+    pub inactivity_period: U64,
+}
+
+// New signers proposed by a recovery account
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveryProposal {
+    pub proposer: AccountId,
+    /// Keys added as signers.
+    pub keys: Vec<PublicKey>,
+    /// Signer keys deleted from the account, e.g. the ones that are lost or compromised.
+    pub removed_keys: Vec<PublicKey>,
+    pub num_confirmations: usize,
+    pub proposed_at: u64,
+    pub executable_at: u64,
+}
+
+/// Request executed repeatedly, created by the `Schedule` action.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledRequest {
+    pub request: MultiSigRequest,
+    pub start: U64,
+    pub interval: U64,
+    /// Number of runs left.
+    pub remaining: u32,
+    /// Runs triggered so far.
+    pub history: Vec<ScheduledRun>,
+}
+
+/// Run of a schedule triggered with `trigger_scheduled`.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledRun {
+    pub timestamp: u64,
+    /// `None` until the outcome of the transaction is known.
+    pub succeeded: Option<bool>,
+}
+
+/// Tokens vesting to the receiver, created by the `CreateStream` action.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stream {
+    /// `None` for NEAR.
+    pub token_id: Option<AccountId>,
+    pub receiver_id: AccountId,
+    pub start: U64,
+    pub cliff: U64,
+    pub end: U64,
+    pub total: U128,
+    /// Amount withdrawn with `claim` so far.
+    pub claimed: U128,
+    /// Claims with transfers in flight, the stream is kept until all of them are resolved.
+    pub pending_claims: u32,
+}
+
+/// How the execution of a request ended.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum RequestOutcome {
+    Succeeded,
+    Failed,
+    /// Success of each transaction of a `Batch` request.
+    Batch {
+        results: Vec<bool>,
+    },
+    /// The request was deleted without execution by its author or by a quorum of members.
+    Deleted {
+        deleted_by: Vec<Member>,
+    },
+}
+
+impl RequestOutcome {
+    pub fn is_success(&self) -> bool {
+        match self {
+            Self::Succeeded => true,
+            Self::Batch { results } => results.iter().all(|is_success| *is_success),
+            Self::Failed | Self::Deleted { .. } => false,
+        }
+    }
+}
+
+/// Result of `delete_request`.
+#[near(serializers=[json])]
+#[derive(Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum DeleteOutcome {
+    /// The request was removed.
+    Deleted { request: MultiSigRequest },
+    /// The vote to delete the request was recorded, it is removed once `num_confirmations` members voted.
+    Voted { votes: u32 },
+}
+
+// An entry of the request history
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestRecord {
+    pub request_id: MultisigRequestId,
+    pub outcome: RequestOutcome,
+    pub timestamp: u64,
+}
+
+/// Limits on retrying failed requests.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryConfig {
+    /// Number of executions of a request including the first one.
+    pub max_attempts: u32,
+    /// Time in nanoseconds after the last failure during which the request can be retried.
+    pub window: U64,
+}
+
+impl Default for RetryConfig {
+    /// 3 attempts within a day after the last failure.
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            window: U64(86_400_000_000_000),
+        }
+    }
+}
+
+// An approved request whose execution failed, kept to be retried with `retry`
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedRequest {
+    pub request: MultiSigRequest,
+    /// Member that added the request, it can delete the failed request alone.
+    pub signer: Member,
+    /// Members that confirmed the request before it was executed.
+    pub confirmations: Vec<Member>,
+    pub attempts: u32,
+    pub failed_at: u64,
+    /// Success of each transaction of a failed `Batch`, a retry executes only the ones that failed.
+    pub batch_results: Option<Vec<bool>>,
+}
+
+// Result of the last action of an executed request as returned by `get_execution_result`
+#[near(serializers=[json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionResult {
+    pub success: bool,
+    /// Returned bytes, empty if the action failed. The runtime doesn't expose the error of a failed action.
+    pub value: Base64VecU8,
+    /// Whether the value was longer than the maximum stored size, it is not stored then.
+    pub truncated: bool,
+    /// The value decoded as JSON, `None` if it is not valid JSON or truncated.
+    pub json: Option<serde_json::Value>,
+}
+
+// Configuration of the multisig as returned by `get_config`
+#[near(serializers=[json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultisigConfig {
+    pub num_confirmations: usize,
+    pub elevated_num_confirmations: usize,
+    /// Number of pending requests each member can have.
+    pub active_requests_limit: u32,
+    /// Time in nanoseconds before a request can be deleted.
+    pub request_cooldown: U64,
+    /// Cooldowns of requests containing actions of the given kinds.
+    pub cooldown_overrides: BTreeMap<MultiSigRequestActionKind, U64>,
+    /// Time in nanoseconds after which a request can't be confirmed anymore, `None` if requests never expire.
+    pub request_expiry: Option<U64>,
+    pub retry_config: RetryConfig,
+    pub confirmations_policy: ConfirmationsPolicy,
+}
+
+/// What happens to confirmations of pending requests when members change or the threshold is raised.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfirmationsPolicy {
+    /// Confirmations stay, including the ones of removed members.
+    Keep,
+    /// Confirmations of removed keys and member accounts are dropped.
+    #[default]
+    DropRemoved,
+    /// All confirmations of pending requests are dropped, they have to be confirmed again.
+    ResetAll,
+}
+
+/// Settings changed by `UpdateConfig`, the ones left out keep their current value.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct ConfigUpdate {
+    pub num_confirmations: Option<usize>,
+    pub elevated_num_confirmations: Option<usize>,
+    pub active_requests_limit: Option<u32>,
+    pub request_cooldown: Option<U64>,
+    /// Replaces all cooldown overrides.
+    pub cooldown_overrides: Option<BTreeMap<MultiSigRequestActionKind, U64>>,
+    /// `0` makes requests never expire.
+    pub request_expiry: Option<U64>,
+    pub retry_config: Option<RetryConfig>,
+    pub policy: Option<Policy>,
+    pub confirmations_policy: Option<ConfirmationsPolicy>,
+    /// Sets or removes the limits of the listed tokens, limits of other tokens are kept.
+    pub spending_limits: Option<Vec<TokenSpendingLimit>>,
+    /// Replaces all guardian keys.
+    pub guardians: Option<Vec<PublicKey>>,
+    /// `null` disables recovery. Drops the pending recovery proposal.
+    #[serde(deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub recovery: Option<Option<RecoveryConfig>>,
+}
+
+/// Spending limit of a token changed by `UpdateConfig`, `None` removes the limit.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenSpendingLimit {
+    pub token_id: Option<AccountId>,
+    pub limit: Option<SpendingLimit>,
+}
+
+// Tells a `null` value apart from a missing field, which keeps the current value
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+impl ConfigUpdate {
+    /// Kinds of the single-setting actions making the same changes, so the policy applies to them alike.
+    pub fn setter_kinds(&self) -> Vec<MultiSigRequestActionKind> {
+        [
+            (
+                self.num_confirmations.is_some(),
+                MultiSigRequestActionKind::SetNumConfirmations,
+            ),
+            (
+                self.elevated_num_confirmations.is_some(),
+                MultiSigRequestActionKind::SetElevatedNumConfirmations,
+            ),
+            (
+                self.active_requests_limit.is_some(),
+                MultiSigRequestActionKind::SetActiveRequestsLimit,
+            ),
+            (
+                self.request_cooldown.is_some() || self.cooldown_overrides.is_some(),
+                MultiSigRequestActionKind::SetRequestCooldown,
+            ),
+            (self.retry_config.is_some(), MultiSigRequestActionKind::SetRetryConfig),
+            (self.policy.is_some(), MultiSigRequestActionKind::SetPolicy),
+            (
+                self.confirmations_policy.is_some(),
+                MultiSigRequestActionKind::SetConfirmationsPolicy,
+            ),
+            (
+                self.spending_limits.is_some(),
+                MultiSigRequestActionKind::SetSpendingLimit,
+            ),
+            (self.guardians.is_some(), MultiSigRequestActionKind::SetGuardians),
+            (self.recovery.is_some(), MultiSigRequestActionKind::SetRecovery),
+        ]
+        .into_iter()
+        .filter_map(|(changed, kind)| changed.then_some(kind))
+        .collect()
+    }
+}
@@ -1,5 +1,7 @@
 mod api;
 mod data;
+mod policy;
 
 pub use api::*;
 pub use data::*;
+pub use policy::*;
@@ -0,0 +1,24 @@
+use std::collections::BTreeMap;
+
+use near_sdk::{near, AccountId, Gas, NearToken};
+
+use crate::data::MultiSigRequestActionKind;
+
+/// Restrictions on requests the multisig can execute.
+/// Checked when a request is added and again when it is executed.
+#[near(serializers=[borsh, json])]
+#[derive(Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Policy {
+    /// Accounts requests can be sent to, any account if `None`.
+    /// Requests to the multisig itself are always allowed, use `forbidden_actions` to restrict them.
+    pub allowed_receivers: Option<Vec<AccountId>>,
+    /// Methods `FunctionCall` can call per receiver. Any method can be called on receivers not listed here.
+    pub allowed_methods: BTreeMap<AccountId, Vec<String>>,
+    /// Maximum deposit attached to a single `FunctionCall`.
+    pub max_deposit: Option<NearToken>,
+    /// Maximum gas attached to a single `FunctionCall`.
+    pub max_gas: Option<Gas>,
+    /// Kinds of actions requests can not contain. `SetPolicy` can not be forbidden.
+    pub forbidden_actions: Vec<MultiSigRequestActionKind>,
+}
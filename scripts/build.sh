@@ -5,5 +5,7 @@ echo ">> Building contract"
 
 rustup target add wasm32-unknown-unknown
 cargo build -p multisig --target wasm32-unknown-unknown --profile=contract
+cargo build -p multisig-factory --target wasm32-unknown-unknown --profile=contract
 
 cp ./target/wasm32-unknown-unknown/contract/multisig.wasm res/multisig.wasm
+cp ./target/wasm32-unknown-unknown/contract/multisig_factory.wasm res/multisig_factory.wasm
@@ -22,3 +22,4 @@ run_clippy() {
 
 run_clippy multisig
 run_clippy multisig-model
+run_clippy multisig-factory